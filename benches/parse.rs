@@ -0,0 +1,112 @@
+//! Parses generated documents of doubling size and checks that the time per byte stays flat, i.e.
+//! that parsing scales linearly. Run with `cargo bench`; pass a shape name to run only that one.
+use std::time::{Duration, Instant};
+
+/// How much slower per byte the largest input may be than the smallest before the run fails. The
+/// inputs grow 8x, so a quadratic parser would be about 8x slower per byte.
+const MAX_SLOWDOWN: f64 = 2.5;
+
+struct Shape {
+    name: &'static str,
+    /// Generates a document with about `n` members.
+    generate: fn(usize) -> String,
+    /// Member count of the smallest input.
+    base: usize,
+}
+
+const SHAPES: &[Shape] = &[
+    Shape { name: "array-of-objects", generate: array_of_objects, base: 20_000 },
+    Shape { name: "large-object", generate: large_object, base: 50_000 },
+    Shape { name: "numbers", generate: numbers, base: 200_000 },
+    Shape { name: "long-strings", generate: long_strings, base: 2_000 },
+    Shape { name: "comments", generate: comments, base: 50_000 },
+    Shape { name: "deep-nesting", generate: deep_nesting, base: 500 },
+    Shape { name: "unclosed-nesting", generate: unclosed_nesting, base: 500 },
+    Shape { name: "malformed", generate: malformed, base: 50_000 },
+];
+
+fn array_of_objects(n: usize) -> String {
+    let items: Vec<_> = (0..n)
+        .map(|i| format!("  {{\"id\": {}, \"name\": \"item {}\", \"score\": {}.5e-3, \"tags\": [true, null, \"x\"]}}", i, i, i))
+        .collect();
+    format!("[\n{}\n]\n", items.join(",\n"))
+}
+
+fn large_object(n: usize) -> String {
+    let pairs: Vec<_> = (0..n).map(|i| format!("  \"key{}\": [{}, \"v\\u00e9\", {{}}]", i, i)).collect();
+    format!("{{\n{}\n}}\n", pairs.join(",\n"))
+}
+
+fn numbers(n: usize) -> String {
+    let items: Vec<_> = (0..n).map(|i| format!("-{}.{}e{}", i, i % 97, i % 9)).collect();
+    format!("[{}]", items.join(","))
+}
+
+fn long_strings(n: usize) -> String {
+    let text = "lorem ipsum dolor sit amet \\\"quoted\\\" \\n ".repeat(20);
+    let items: Vec<_> = (0..n).map(|_| format!("\"{}\"", text)).collect();
+    format!("[{}]", items.join(",\n"))
+}
+
+fn comments(n: usize) -> String {
+    let items: Vec<_> = (0..n).map(|i| format!("  // item {}\n  {} /* inline */", i, i)).collect();
+    format!("[\n{}\n]\n", items.join(",\n"))
+}
+
+fn deep_nesting(n: usize) -> String {
+    format!("{}{}{}", "[{\"a\": ".repeat(n), 1, "}]".repeat(n))
+}
+
+/// Every container is missing its closing bracket, so each one fails at the end of the input.
+fn unclosed_nesting(n: usize) -> String {
+    "[1, {\"a\": ".repeat(n)
+}
+
+/// Every member is broken in some way, which exercises error recovery.
+fn malformed(n: usize) -> String {
+    let items: Vec<_> = (0..n).map(|i| format!("{{\"a\" {} \"b\": tru, \"c\": \"open\n}}", i)).collect();
+    format!("[{}", items.join(" "))
+}
+
+/// The fastest of a few runs, to filter out noise.
+fn measure(text: &str) -> Duration {
+    // The deep shapes nest past the default limit, which would skip most of them.
+    let options = lst::ParseOptions { max_depth: usize::MAX, ..Default::default() };
+    (0..5)
+        .map(|_| {
+            let content = text.to_string();
+            let start = Instant::now();
+            let source = lst::parse_with(content, options);
+            let elapsed = start.elapsed();
+            drop(source);
+            elapsed
+        })
+        .min()
+        .unwrap()
+}
+
+fn main() {
+    // `cargo bench` passes `--bench`; any other argument selects shapes by name.
+    let filter: Vec<String> = std::env::args().skip(1).filter(|a| !a.starts_with("--")).collect();
+    let mut failed = Vec::new();
+    for shape in SHAPES.iter().filter(|s| filter.is_empty() || filter.iter().any(|f| s.name.contains(f.as_str()))) {
+        let mut per_byte = Vec::new();
+        for factor in [1, 2, 4, 8] {
+            let text = (shape.generate)(shape.base * factor);
+            let elapsed = measure(&text);
+            let ns = elapsed.as_nanos() as f64 / text.len() as f64;
+            let throughput = text.len() as f64 / elapsed.as_secs_f64() / 1e6;
+            println!("{:<18} {:>10} bytes {:>12.3?} {:>8.1} MB/s {:>6.2} ns/byte", shape.name, text.len(), elapsed, throughput, ns);
+            per_byte.push(ns);
+        }
+        let slowdown = per_byte[per_byte.len() - 1] / per_byte[0];
+        println!("{:<18} {:.2}x time per byte from smallest to largest\n", shape.name, slowdown);
+        if slowdown > MAX_SLOWDOWN {
+            failed.push(shape.name);
+        }
+    }
+    if !failed.is_empty() {
+        eprintln!("parsing does not scale linearly for: {}", failed.join(", "));
+        std::process::exit(1);
+    }
+}
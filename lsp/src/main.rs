@@ -0,0 +1,20 @@
+use std::io;
+use server::Server;
+
+mod server;
+mod transport;
+
+fn main() -> io::Result<()> {
+    let mut reader = io::stdin().lock();
+    let mut writer = io::stdout().lock();
+    let mut server = Server::default();
+    while let Some(message) = transport::read_message(&mut reader)? {
+        for response in server.handle(message) {
+            transport::write_message(&mut writer, &response)?;
+        }
+        if let Some(code) = server.exit_code() {
+            std::process::exit(code);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,295 @@
+use std::collections::HashMap;
+use lst::ast::{Array, Object};
+use lst::{Dialect, LineIndex, ParseOptions, Severity, Source, SyntaxKind, SyntaxNode, TextEdit, TextRange};
+use serde_json::{json, Value};
+
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+
+struct OpenDocument {
+    source: Source,
+    index: LineIndex,
+}
+
+impl OpenDocument {
+    fn new(text: String, options: ParseOptions) -> Self {
+        let index = LineIndex::new(&text);
+        Self { source: lst::parse_with(text, options), index }
+    }
+
+    fn offset(&self, position: &Value) -> Option<u32> {
+        let line = position["line"].as_u64()? as u32;
+        let character = position["character"].as_u64()? as u32;
+        Some(self.index.offset_utf16(&self.source.content, line, character))
+    }
+
+    fn position(&self, offset: u32) -> Value {
+        let p = self.index.position(&self.source.content, offset);
+        json!({ "line": p.line, "character": p.column_utf16 })
+    }
+
+    fn range(&self, range: TextRange) -> Value {
+        json!({ "start": self.position(range.start), "end": self.position(range.end) })
+    }
+}
+
+/// Handles decoded JSON-RPC messages and produces the messages to send back. Documents are synced in
+/// full on every change and kept parsed.
+#[derive(Default)]
+pub struct Server {
+    documents: HashMap<String, OpenDocument>,
+    shutdown: bool,
+    exit: Option<i32>,
+}
+
+impl Server {
+    pub fn exit_code(&self) -> Option<i32> {
+        self.exit
+    }
+
+    pub fn handle(&mut self, message: Value) -> Vec<Value> {
+        // A message without a method is the client's response to a request; the server sends none.
+        let Some(method) = message["method"].as_str() else {
+            return vec![];
+        };
+        let params = &message["params"];
+        let Some(id) = message.get("id").cloned() else {
+            return self.notification(method, params);
+        };
+        let result = match method {
+            "initialize" => Ok(initialize()),
+            "shutdown" => {
+                self.shutdown = true;
+                Ok(Value::Null)
+            }
+            "textDocument/onTypeFormatting" => self.with_document(params, on_type_formatting),
+            "textDocument/documentSymbol" => self.with_document(params, document_symbol),
+            "textDocument/foldingRange" => self.with_document(params, folding_range),
+            "textDocument/selectionRange" => self.with_document(params, selection_range),
+            _ => Err((METHOD_NOT_FOUND, format!("unhandled method {}", method))),
+        };
+        vec![match result {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err((code, message)) => json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message } }),
+        }]
+    }
+
+    fn notification(&mut self, method: &str, params: &Value) -> Vec<Value> {
+        let uri = params["textDocument"]["uri"].as_str().unwrap_or_default().to_string();
+        match method {
+            "exit" => {
+                self.exit = Some(if self.shutdown { 0 } else { 1 });
+                vec![]
+            }
+            "textDocument/didOpen" => {
+                let text = params["textDocument"]["text"].as_str().unwrap_or_default();
+                let options = match params["textDocument"]["languageId"].as_str() {
+                    Some("json") => ParseOptions::new(Dialect::Json),
+                    Some("jsonc") => ParseOptions::new(Dialect::Jsonc),
+                    Some("json5") => ParseOptions::new(Dialect::Json5),
+                    _ => ParseOptions::default(),
+                };
+                self.documents.insert(uri.clone(), OpenDocument::new(text.to_string(), options));
+                vec![self.publish_diagnostics(&uri)]
+            }
+            "textDocument/didChange" => {
+                let Some(text) = params["contentChanges"].as_array().and_then(|c| c.last()).and_then(|c| c["text"].as_str()) else {
+                    return vec![];
+                };
+                let options = self.documents.get(&uri).map(|d| d.source.options).unwrap_or_default();
+                self.documents.insert(uri.clone(), OpenDocument::new(text.to_string(), options));
+                vec![self.publish_diagnostics(&uri)]
+            }
+            "textDocument/didClose" => {
+                self.documents.remove(&uri);
+                vec![notification("textDocument/publishDiagnostics", json!({ "uri": uri, "diagnostics": [] }))]
+            }
+            _ => vec![],
+        }
+    }
+
+    fn with_document(&self, params: &Value, f: impl Fn(&OpenDocument, &Value) -> Option<Value>) -> Result<Value, (i64, String)> {
+        let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+        let document = self.documents.get(uri).ok_or_else(|| (INVALID_PARAMS, format!("unknown document {}", uri)))?;
+        Ok(f(document, params).unwrap_or(Value::Null))
+    }
+
+    fn publish_diagnostics(&self, uri: &str) -> Value {
+        let document = &self.documents[uri];
+        let diagnostics: Vec<Value> = document
+            .source
+            .diagnostics()
+            .into_iter()
+            .map(|d| {
+                json!({
+                    "range": document.range(d.range),
+                    "severity": match d.severity {
+                        Severity::Error => 1,
+                        Severity::Warning => 2,
+                    },
+                    "code": d.code.as_str(),
+                    "source": "lst",
+                    "message": d.message,
+                })
+            })
+            .collect();
+        notification("textDocument/publishDiagnostics", json!({ "uri": uri, "diagnostics": diagnostics }))
+    }
+}
+
+fn notification(method: &str, params: Value) -> Value {
+    json!({ "jsonrpc": "2.0", "method": method, "params": params })
+}
+
+fn initialize() -> Value {
+    json!({
+        "capabilities": {
+            "textDocumentSync": 1,
+            "documentOnTypeFormattingProvider": { "firstTriggerCharacter": "\n" },
+            "documentSymbolProvider": true,
+            "foldingRangeProvider": true,
+            "selectionRangeProvider": true,
+        },
+        "serverInfo": { "name": "lst-lsp", "version": env!("CARGO_PKG_VERSION") },
+    })
+}
+
+fn edits(document: &OpenDocument, edits: Vec<TextEdit>) -> Value {
+    edits.into_iter().map(|e| json!({ "range": document.range(e.range), "newText": e.new_text })).collect()
+}
+
+/// The nodes below `node` in document order.
+fn descendants(node: SyntaxNode<'_>) -> Vec<SyntaxNode<'_>> {
+    let mut out = Vec::new();
+    let mut stack: Vec<_> = node.children().rev().collect();
+    while let Some(node) = stack.pop() {
+        stack.extend(node.children().rev());
+        out.push(node);
+    }
+    out
+}
+
+/// The comma and indentation logic lives in [`lst::assist::on_newline`].
+fn on_type_formatting(document: &OpenDocument, params: &Value) -> Option<Value> {
+    let offset = document.offset(&params["position"])?;
+    Some(edits(document, lst::assist::on_newline(&document.source, offset)))
+}
+
+fn symbol_kind(kind: SyntaxKind) -> u32 {
+    match kind {
+        SyntaxKind::Object => 19,
+        SyntaxKind::Array => 18,
+        SyntaxKind::String => 15,
+        SyntaxKind::Number => 16,
+        SyntaxKind::Boolean => 17,
+        SyntaxKind::Null => 21,
+        _ => 13,
+    }
+}
+
+/// The members of a container: each one's name, range, the range to select and its value.
+fn members<'a>(value: &SyntaxNode<'a>) -> Vec<(String, TextRange, TextRange, SyntaxNode<'a>)> {
+    if let Some(object) = Object::cast(value.clone()) {
+        object
+            .pairs()
+            .filter_map(|pair| {
+                // Clients reject a symbol without a name, so an empty key is shown quoted.
+                let name = match pair.key_text()? {
+                    key if key.is_empty() => "\"\"".to_string(),
+                    key => key.to_string(),
+                };
+                let key = pair.key()?.text_range();
+                Some((name, pair.syntax().text_range(), key, pair.value()?))
+            })
+            .collect()
+    } else if let Some(array) = Array::cast(value.clone()) {
+        array.iter().enumerate().map(|(i, value)| (i.to_string(), value.text_range(), value.text_range(), value)).collect()
+    } else {
+        vec![]
+    }
+}
+
+/// The symbols for the members of `value`, with the symbols for their members as children. The
+/// symbols are listed parents first with an explicit stack, then nested from the last one back.
+fn symbols(document: &OpenDocument, value: &SyntaxNode<'_>) -> Vec<Value> {
+    let mut listed: Vec<(Option<usize>, Value)> = Vec::new();
+    let mut stack: Vec<_> = members(value).into_iter().rev().map(|member| (None, member)).collect();
+    while let Some((parent, (name, range, selection, value))) = stack.pop() {
+        stack.extend(members(&value).into_iter().rev().map(|member| (Some(listed.len()), member)));
+        listed.push((
+            parent,
+            json!({
+                "name": name,
+                "kind": symbol_kind(value.kind()),
+                "range": document.range(range),
+                "selectionRange": document.range(selection),
+            }),
+        ));
+    }
+    let mut children = vec![Vec::new(); listed.len()];
+    let mut roots = Vec::new();
+    for (i, (parent, mut symbol)) in listed.into_iter().enumerate().rev() {
+        let mut own = std::mem::take(&mut children[i]);
+        own.reverse();
+        symbol["children"] = Value::Array(own);
+        match parent {
+            Some(parent) => children[parent].push(symbol),
+            None => roots.push(symbol),
+        }
+    }
+    roots.reverse();
+    roots
+}
+
+/// Object keys as a hierarchy; array elements appear under their index.
+fn document_symbol(document: &OpenDocument, _: &Value) -> Option<Value> {
+    Some(Value::Array(symbols(document, &document.source.root().first_child()?)))
+}
+
+/// Every object and array spanning more than one line folds from its opening line to the line
+/// before its closing bracket.
+fn folding_range(document: &OpenDocument, _: &Value) -> Option<Value> {
+    let content = &document.source.content;
+    let ranges = descendants(document.source.root())
+        .into_iter()
+        .filter(|n| matches!(n.kind(), SyntaxKind::Object | SyntaxKind::Array))
+        .filter_map(|n| {
+            let range = n.text_range();
+            let start = document.index.position(content, range.start).line;
+            let end = document.index.position(content, range.end).line;
+            (end > start + 1).then(|| json!({ "startLine": start, "endLine": end - 1 }))
+        })
+        .collect();
+    Some(Value::Array(ranges))
+}
+
+/// For each position, the chain of enclosing nodes from the innermost one up to the document.
+fn selection_range(document: &OpenDocument, params: &Value) -> Option<Value> {
+    let positions = params["positions"].as_array()?;
+    let mut result = Vec::new();
+    for position in positions {
+        let offset = document.offset(position)?;
+        let mut chain = vec![document.source.root()];
+        while let Some(child) = chain.last().unwrap().children().find(|c| {
+            let range = c.text_range();
+            range.start <= offset && offset <= range.end
+        }) {
+            chain.push(child);
+        }
+        let mut selection = Value::Null;
+        let mut last = None;
+        for node in chain {
+            // Pairs and items can share a range with their value; one step is enough for those.
+            if last == Some(node.text_range()) {
+                continue;
+            }
+            last = Some(node.text_range());
+            selection = match selection {
+                Value::Null => json!({ "range": document.range(node.text_range()) }),
+                parent => json!({ "range": document.range(node.text_range()), "parent": parent }),
+            };
+        }
+        result.push(selection);
+    }
+    Some(Value::Array(result))
+}
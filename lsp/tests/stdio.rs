@@ -0,0 +1,176 @@
+use std::io::{BufReader, Write};
+use std::process::{Command, Stdio};
+use serde_json::{json, Value};
+
+#[path = "../src/transport.rs"]
+mod transport;
+
+/// Feeds a scripted session to the server over stdio and returns everything it wrote back.
+fn session(messages: &[Value]) -> (Vec<Value>, i32) {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_lst-lsp"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut stdin = child.stdin.take().unwrap();
+    for message in messages {
+        transport::write_message(&mut stdin, message).unwrap();
+    }
+    stdin.flush().unwrap();
+    drop(stdin);
+    let mut stdout = BufReader::new(child.stdout.take().unwrap());
+    let mut received = Vec::new();
+    while let Some(message) = transport::read_message(&mut stdout).unwrap() {
+        received.push(message);
+    }
+    (received, child.wait().unwrap().code().unwrap())
+}
+
+fn request(id: u64, method: &str, params: Value) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params })
+}
+
+fn notification(method: &str, params: Value) -> Value {
+    json!({ "jsonrpc": "2.0", "method": method, "params": params })
+}
+
+fn response(received: &[Value], id: u64) -> &Value {
+    &received.iter().find(|m| m["id"] == id).unwrap()["result"]
+}
+
+const URI: &str = "file:///config.json";
+
+fn open(text: &str) -> Value {
+    open_as("json", text)
+}
+
+fn open_as(language: &str, text: &str) -> Value {
+    notification("textDocument/didOpen", json!({
+        "textDocument": { "uri": URI, "languageId": language, "version": 1, "text": text },
+    }))
+}
+
+fn doc() -> Value {
+    json!({ "uri": URI })
+}
+
+#[test]
+fn test_lifecycle_and_diagnostics() {
+    let (received, code) = session(&[
+        request(1, "initialize", json!({ "capabilities": {} })),
+        notification("initialized", json!({})),
+        open("{\n  \"a\": 1\n  \"b\": tru\n}"),
+        request(2, "shutdown", Value::Null),
+        notification("exit", Value::Null),
+    ]);
+    assert_eq!(code, 0);
+    let capabilities = &response(&received, 1)["capabilities"];
+    assert_eq!(capabilities["documentOnTypeFormattingProvider"]["firstTriggerCharacter"], "\n");
+
+    let published = received.iter().find(|m| m["method"] == "textDocument/publishDiagnostics").unwrap();
+    let diagnostics = published["params"]["diagnostics"].as_array().unwrap();
+    let codes: Vec<_> = diagnostics.iter().map(|d| d["code"].as_str().unwrap()).collect();
+    assert_eq!(codes, vec!["missing-comma", "invalid-literal"]);
+    assert_eq!(diagnostics[1]["range"], json!({
+        "start": { "line": 2, "character": 7 },
+        "end": { "line": 2, "character": 10 },
+    }));
+}
+
+#[test]
+fn test_language_selects_dialect() {
+    let codes = |language: &str| {
+        let (received, _) = session(&[open_as(language, "[1, // one\n 'two']")]);
+        let published = received.iter().find(|m| m["method"] == "textDocument/publishDiagnostics").unwrap();
+        let diagnostics = published["params"]["diagnostics"].as_array().unwrap().clone();
+        diagnostics.iter().map(|d| d["code"].as_str().unwrap().to_string()).collect::<Vec<_>>()
+    };
+    assert_eq!(codes("json"), vec!["comment", "invalid-value"]);
+    assert_eq!(codes("jsonc"), vec!["invalid-value"]);
+    assert!(codes("json5").is_empty());
+}
+
+#[test]
+fn test_exit_without_shutdown() {
+    let (_, code) = session(&[notification("exit", Value::Null)]);
+    assert_eq!(code, 1);
+}
+
+#[test]
+fn test_on_type_formatting_inserts_comma() {
+    let (received, _) = session(&[
+        open("{\n  \"a\": 1\n  \n}"),
+        request(1, "textDocument/onTypeFormatting", json!({
+            "textDocument": doc(),
+            "position": { "line": 2, "character": 2 },
+            "ch": "\n",
+            "options": { "tabSize": 2, "insertSpaces": true },
+        })),
+        // In front of the closing brace there is nothing to separate.
+        request(2, "textDocument/onTypeFormatting", json!({
+            "textDocument": doc(),
+            "position": { "line": 3, "character": 0 },
+            "ch": "\n",
+            "options": { "tabSize": 2, "insertSpaces": true },
+        })),
+    ]);
+    assert_eq!(response(&received, 1), &json!([{
+        "range": { "start": { "line": 1, "character": 8 }, "end": { "line": 1, "character": 8 } },
+        "newText": ",",
+    }]));
+    assert_eq!(response(&received, 2), &json!([]));
+}
+
+#[test]
+fn test_document_symbols_and_folding() {
+    let (received, _) = session(&[
+        open("{\n  \"a\": {\n    \"b\": [\n      1\n    ]\n  },\n  \"c\": \"x\",\n  \"\": 2\n}"),
+        request(1, "textDocument/documentSymbol", json!({ "textDocument": doc() })),
+        request(2, "textDocument/foldingRange", json!({ "textDocument": doc() })),
+    ]);
+    let symbols = response(&received, 1).as_array().unwrap();
+    let names: Vec<_> = symbols.iter().map(|s| s["name"].as_str().unwrap()).collect();
+    // Clients reject a symbol with an empty name.
+    assert_eq!(names, vec!["a", "c", "\"\""]);
+    assert_eq!(symbols[0]["kind"], 19);
+    assert_eq!(symbols[0]["selectionRange"]["start"], json!({ "line": 1, "character": 2 }));
+    assert_eq!(symbols[0]["children"][0]["name"], "b");
+    assert_eq!(symbols[0]["children"][0]["kind"], 18);
+    assert_eq!(symbols[0]["children"][0]["children"][0]["name"], "0");
+
+    assert_eq!(response(&received, 2), &json!([
+        { "startLine": 0, "endLine": 7 },
+        { "startLine": 1, "endLine": 4 },
+        { "startLine": 2, "endLine": 3 },
+    ]));
+}
+
+#[test]
+fn test_selection_range() {
+    let (received, _) = session(&[
+        open("{\"a\": [10, 20]}"),
+        request(1, "textDocument/selectionRange", json!({
+            "textDocument": doc(),
+            "positions": [{ "line": 0, "character": 12 }],
+        })),
+    ]);
+    let mut ranges = Vec::new();
+    let mut selection = &response(&received, 1)[0];
+    while !selection.is_null() {
+        let range = &selection["range"];
+        ranges.push((range["start"]["character"].as_u64().unwrap(), range["end"]["character"].as_u64().unwrap()));
+        selection = &selection["parent"];
+    }
+    assert_eq!(ranges, vec![(11, 13), (6, 14), (1, 14), (0, 15)]);
+}
+
+#[test]
+fn test_unknown_request() {
+    let (received, _) = session(&[
+        json!({ "jsonrpc": "2.0", "id": 3, "result": null }),
+        request(7, "workspace/symbol", json!({ "query": "" })),
+    ]);
+    // A response from the client gets no reply.
+    assert_eq!(received.len(), 1);
+    assert_eq!(received[0]["error"]["code"], -32601);
+}
@@ -0,0 +1,119 @@
+//! Editor assists that are independent of any LSP transport.
+use crate::edit::{indent_unit, line_indent, line_start, starts_line, TextEdit};
+use crate::range::TextRange;
+use crate::source::Source;
+use crate::syntax::{SyntaxKind, SyntaxNode};
+
+/// Computes the edits to make after a newline was typed, with `offset` being the cursor position
+/// right after it (and after any indentation the editor inserted itself).
+///
+/// If the newline directly follows a pair or item that has no trailing comma, the user is about to
+/// write the next member, so a comma is inserted after it. The exception is when the cursor sits in
+/// front of the closing bracket of the container, because then the newline only moved the bracket
+/// down. The new line is also re-indented: to the previous member's indentation, or to that of the
+/// line with the opening bracket when the closing bracket follows.
+pub fn on_newline(source: &Source, offset: u32) -> Vec<TextEdit> {
+    let content = source.content.as_str();
+    let offset = offset.min(content.len() as u32);
+    let Some(newline) = content[..offset as usize].rfind('\n').map(|i| i as u32) else {
+        return vec![];
+    };
+    let Some(container) = container_at(source, newline) else {
+        return vec![];
+    };
+    let range = container.text_range();
+    let closer = match container.kind() {
+        SyntaxKind::Object => '}',
+        _ => ']',
+    };
+    let line_end = content[offset as usize..].find('\n').map(|i| offset as usize + i).unwrap_or(content.len());
+    let before_closer = content[offset as usize..line_end].trim_start().starts_with(closer);
+    let previous = container.children().filter(|m| m.text_range().end <= newline).last();
+
+    let mut edits = Vec::new();
+    if let Some(previous) = &previous {
+        let between = content[previous.text_range().end as usize..newline as usize].trim_start_matches([' ', '\t']);
+        let directly_before = between.is_empty() || (between.starts_with("//") && !between.contains('\n'));
+        if directly_before && !before_closer && needs_comma(previous) {
+            edits.push(TextEdit::insert(previous.text_range().end, ","));
+        }
+    }
+
+    let indent = match &previous {
+        _ if before_closer => line_indent(content, range.start).to_string(),
+        Some(previous) if starts_line(content, previous.text_range().start) => {
+            line_indent(content, previous.text_range().start).to_string()
+        }
+        _ => format!("{}{}", line_indent(content, range.start), indent_unit(content)),
+    };
+    let start = line_start(content, offset) as u32;
+    let current = line_indent(content, start);
+    if current != indent {
+        edits.push(TextEdit::replace(TextRange::at(start, current.len() as u32), indent));
+    }
+    edits
+}
+
+/// The innermost object or array whose brackets enclose `offset`.
+fn container_at(source: &Source, offset: u32) -> Option<SyntaxNode<'_>> {
+    source.node_at(offset).ancestors().find(|node| {
+        let range = node.text_range();
+        matches!(node.kind(), SyntaxKind::Object | SyntaxKind::Array) && range.start < offset && offset < range.end
+    })
+}
+
+/// A member needs a comma if it has none and parsed cleanly; a comma after a half-typed pair would
+/// only add to the confusion.
+fn needs_comma(member: &SyntaxNode<'_>) -> bool {
+    let Some(value) = member.children().last() else {
+        return false;
+    };
+    member.text_range().end == value.text_range().end && !member.has_error()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+
+    /// Applies `on_newline` at the `|` marker and returns the resulting text, with the marker removed.
+    fn assist(text: &str) -> String {
+        let offset = text.find('|').unwrap() as u32;
+        let text = text.replace('|', "");
+        let source = parse(text.clone());
+        crate::apply_edits(&text, &on_newline(&source, offset))
+    }
+
+    #[test]
+    fn test_comma_after_pair() {
+        assert_eq!(assist("{\n  \"a\": 1\n  |\n}"), "{\n  \"a\": 1,\n  \n}");
+        assert_eq!(assist("{\n  \"a\": 1\n|\n}"), "{\n  \"a\": 1,\n  \n}");
+        assert_eq!(assist("{\n  \"a\": 1, \n|\n}"), "{\n  \"a\": 1, \n  \n}");
+    }
+
+    #[test]
+    fn test_comma_after_item() {
+        assert_eq!(assist("[\n\t[1, 2]\n|]"), "[\n\t[1, 2]\n]");
+        assert_eq!(assist("[\n\t[1, 2]\n|\n]"), "[\n\t[1, 2],\n\t\n]");
+        assert_eq!(assist("[\n\ttrue // yes\n|\n]"), "[\n\ttrue, // yes\n\t\n]");
+    }
+
+    #[test]
+    fn test_before_closer() {
+        assert_eq!(assist("{\"a\": {\"b\": 1\n|}}"), "{\"a\": {\"b\": 1\n}}");
+        assert_eq!(assist("{\n  \"a\": {\"b\": 1\n      |}\n}"), "{\n  \"a\": {\"b\": 1\n  }\n}");
+    }
+
+    #[test]
+    fn test_indents_first_member() {
+        assert_eq!(assist("{\n|\"x\": 0\n}\n"), "{\n  \"x\": 0\n}\n");
+        assert_eq!(assist("{\"a\": [\n|\n]}"), "{\"a\": [\n  \n]}");
+    }
+
+    #[test]
+    fn test_no_comma_after_broken_pair() {
+        assert_eq!(assist("{\n  \"a\":\n  |\n}"), "{\n  \"a\":\n  \n}");
+        assert_eq!(assist("{\n  \"a\" 1\n  |\n}"), "{\n  \"a\" 1\n  \n}");
+        assert_eq!(assist("{\n  \"a\": 1,\n  \"b\": 2\n  |\n  \"c\": 3\n}"), "{\n  \"a\": 1,\n  \"b\": 2,\n  \n  \"c\": 3\n}");
+    }
+}
@@ -0,0 +1,160 @@
+//! Typed views over [`SyntaxNode`]s. Each wrapper is a `SyntaxNode` of a known kind, so the
+//! accessors here only ever compute absolute positions, never re-parse text.
+use std::borrow::Cow;
+use crate::escape;
+use crate::syntax::{SyntaxKind, SyntaxNode};
+
+macro_rules! define_ast_node {
+    ($name:ident, $kind:ident) => {
+        #[derive(Debug, Clone, PartialEq)]
+        pub struct $name<'a>(SyntaxNode<'a>);
+
+        impl<'a> $name<'a> {
+            pub fn cast(node: SyntaxNode<'a>) -> Option<Self> {
+                (node.kind() == SyntaxKind::$kind).then_some(Self(node))
+            }
+
+            pub fn syntax(&self) -> &SyntaxNode<'a> {
+                &self.0
+            }
+        }
+    };
+}
+
+define_ast_node!(Object, Object);
+define_ast_node!(Array, Array);
+define_ast_node!(Pair, Pair);
+define_ast_node!(Item, Item);
+define_ast_node!(StringToken, String);
+
+impl<'a> Object<'a> {
+    pub fn pairs(&self) -> impl Iterator<Item = Pair<'a>> {
+        self.0.children().filter_map(Pair::cast)
+    }
+
+    /// Decoded keys in document order. Pairs with a malformed key are skipped.
+    pub fn keys(&self) -> impl Iterator<Item = Cow<'a, str>> {
+        self.pairs().filter_map(|p| p.key_text())
+    }
+
+    /// The value of the first pair whose decoded key is `key`.
+    pub fn get(&self, key: &str) -> Option<SyntaxNode<'a>> {
+        self.pairs().find(|p| p.key_text().as_deref() == Some(key))?.value()
+    }
+}
+
+impl<'a> Array<'a> {
+    pub fn items(&self) -> impl Iterator<Item = Item<'a>> {
+        self.0.children().filter_map(Item::cast)
+    }
+
+    /// The values of the array, without their commas.
+    pub fn iter(&self) -> impl Iterator<Item = SyntaxNode<'a>> {
+        self.items().filter_map(|i| i.value())
+    }
+
+    pub fn get(&self, index: usize) -> Option<SyntaxNode<'a>> {
+        self.iter().nth(index)
+    }
+
+    pub fn len(&self) -> usize {
+        self.items().count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<'a> Pair<'a> {
+    pub fn key(&self) -> Option<SyntaxNode<'a>> {
+        self.0.children().next()
+    }
+
+    /// The decoded key, or `None` if the key is malformed.
+    pub fn key_text(&self) -> Option<Cow<'a, str>> {
+        let key = self.key()?;
+        if key.kind() == SyntaxKind::Identifier {
+            return Some(Cow::Borrowed(key.text()));
+        }
+        Some(StringToken::cast(key)?.value())
+    }
+
+    pub fn value(&self) -> Option<SyntaxNode<'a>> {
+        self.0.children().nth(1)
+    }
+}
+
+impl<'a> Item<'a> {
+    pub fn value(&self) -> Option<SyntaxNode<'a>> {
+        self.0.children().next()
+    }
+}
+
+impl<'a> StringToken<'a> {
+    /// The decoded contents, borrowed from the source unless the string has escapes. Malformed
+    /// escapes decode to U+FFFD.
+    pub fn value(&self) -> Cow<'a, str> {
+        escape::unescape(self.0.text(), self.0.options().json5())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+    use crate::range::TextRange;
+
+    #[test]
+    fn test_navigation() {
+        let source = parse(include_str!("../tests/data/multi.json").to_string());
+        let root = source.root();
+        assert_eq!(root.kind(), SyntaxKind::Root);
+        let object = Object::cast(root.first_child().unwrap()).unwrap();
+        assert_eq!(object.keys().collect::<Vec<_>>(), vec!["a", "b", "c", "d", "e"]);
+        assert!(object.keys().all(|k| matches!(k, Cow::Borrowed(_))));
+        assert_eq!(object.get("b").unwrap().text(), "1");
+
+        let d = Array::cast(object.get("d").unwrap()).unwrap();
+        assert_eq!(d.len(), 5);
+        let texts: Vec<_> = d.iter().map(|v| v.text()).collect();
+        assert_eq!(texts, vec!["1", "\"two\"", "true", "{ \"e\": \"f\" }", "3.0"]);
+
+        let inner = Object::cast(d.get(3).unwrap()).unwrap();
+        let f = inner.get("e").unwrap();
+        assert_eq!(f.text_range(), TextRange::new(68, 71));
+        assert_eq!(&source.content[68..71], "\"f\"");
+        let kinds: Vec<_> = f.ancestors().map(|n| n.kind()).collect();
+        assert_eq!(kinds, vec![
+            SyntaxKind::String,
+            SyntaxKind::Pair,
+            SyntaxKind::Object,
+            SyntaxKind::Item,
+            SyntaxKind::Array,
+            SyntaxKind::Pair,
+            SyntaxKind::Object,
+            SyntaxKind::Root,
+        ]);
+    }
+
+    #[test]
+    fn test_pair_and_item_ranges() {
+        let source = parse("{ \"a\" : 1 , \"b\": [ 2 ,3 ] }".to_string());
+        let object = Object::cast(source.root().first_child().unwrap()).unwrap();
+        let pairs: Vec<_> = object.pairs().map(|p| p.syntax().text()).collect();
+        assert_eq!(pairs, vec!["\"a\" : 1 ,", "\"b\": [ 2 ,3 ]"]);
+        let array = Array::cast(object.get("b").unwrap()).unwrap();
+        let items: Vec<_> = array.items().map(|i| i.syntax().text()).collect();
+        assert_eq!(items, vec!["2 ,", "3"]);
+    }
+
+    #[test]
+    fn test_decoded_keys_and_values() {
+        let source = parse(r#"{"a\u0062": "x\ty", "\"q\"": 1}"#.to_string());
+        let object = Object::cast(source.root().first_child().unwrap()).unwrap();
+        assert_eq!(object.keys().collect::<Vec<_>>(), vec!["ab", "\"q\""]);
+        let value = StringToken::cast(object.get("ab").unwrap()).unwrap();
+        assert_eq!(value.value(), "x\ty");
+        assert_eq!(object.get("\"q\"").unwrap().text(), "1");
+    }
+}
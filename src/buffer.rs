@@ -1,24 +1,24 @@
+use crate::options::ParseOptions;
+
 #[derive(Debug, Clone)]
 pub struct Buffer<'a> {
     pub content: &'a str,
+    pub options: ParseOptions,
+}
+
+impl<'a> Buffer<'a> {
+    pub fn new(content: &'a str, options: ParseOptions) -> Self {
+        Self { content, options }
+    }
 }
 
 impl Buffer<'_> {
     pub fn offset(&self, offset: u32) -> Self {
         Self {
             content: &self.content[offset as usize..],
+            options: self.options,
         }
     }
-
-    pub fn cutoff(&self, len: u32) -> Self {
-        Self {
-            content: &self.content[..len as usize],
-        }
-    }
-
-    // pub fn slice_start(&mut self, len: u32) {
-    //     self.content = &self.content[len as usize..];
-    // }
 }
 
 impl std::ops::Deref for Buffer<'_> {
@@ -27,4 +27,4 @@ impl std::ops::Deref for Buffer<'_> {
     fn deref(&self) -> &Self::Target {
         self.content
     }
-}
\ No newline at end of file
+}
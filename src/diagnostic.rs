@@ -0,0 +1,403 @@
+use std::borrow::Cow;
+use std::collections::HashSet;
+use crate::line_index::{LineIndex, Position};
+use crate::options::{ParseOptions, Policy};
+use crate::parse::{self, ErrorCode};
+use crate::range::TextRange;
+use crate::source::Source;
+use crate::escape;
+use crate::green::GreenNode;
+use crate::tokens::{self, Array, Item, Key, Node, Object, Offset, Pair, Root, Trivia, Value};
+use crate::trivia;
+use crate::width::Width;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub code: ErrorCode,
+    pub severity: Severity,
+    pub message: Cow<'static, str>,
+    pub range: TextRange,
+    pub start: Position,
+    pub end: Position,
+}
+
+/// Collects diagnostics in document order. Spans are derived from the widths stored in the tree, so
+/// this is a single walk over the tree plus a line lookup per diagnostic.
+pub(crate) fn collect(source: &Source) -> Vec<Diagnostic> {
+    let index = LineIndex::new(&source.content);
+    raw(&source.content, &source.dom, source.options)
+        .into_iter()
+        .map(|(code, severity, message, range)| Diagnostic {
+            code,
+            severity,
+            message,
+            range,
+            start: index.position(&source.content, range.start),
+            end: index.position(&source.content, range.end),
+        })
+        .collect()
+}
+
+/// Whether the tree has a parse error, or `text` breaks a policy that is set to [`Policy::Error`].
+pub(crate) fn has_error(text: &str, dom: &GreenNode, options: ParseOptions) -> bool {
+    // Trailing content is an error in the tree, but it is up to the policy.
+    Root::new(dom).value.1 .1.has_error() || raw(text, dom, options).iter().any(|(_, severity, _, _)| *severity == Severity::Error)
+}
+
+fn raw(text: &str, dom: &GreenNode, options: ParseOptions) -> Vec<Raw> {
+    let mut collector = Collector { text, options, raw: Vec::new() };
+    let root = Root::new(dom);
+    collector.trivia(0, root.value.0);
+    collector.value(&root.value.1, root.value.0);
+    collector.trailing(&root.trailing, root.value.width());
+    collector.raw
+}
+
+type Raw = (ErrorCode, Severity, Cow<'static, str>, TextRange);
+
+struct Collector<'a> {
+    text: &'a str,
+    options: ParseOptions,
+    raw: Vec<Raw>,
+}
+
+/// An object or array that the walk is inside of.
+struct Frame<'g, 'a> {
+    members: Members<'g>,
+    /// How many members have been started.
+    index: usize,
+    offset: u32,
+    /// The trivia in front of the closing bracket.
+    close: u32,
+    keys: HashSet<Cow<'a, str>>,
+}
+
+enum Members<'g> {
+    Pairs(Vec<Pair<'g>>),
+    Items(Vec<Item<'g>>),
+}
+
+impl<'g> Frame<'g, '_> {
+    fn object(object: &Object<'g>, offset: u32) -> Self {
+        let offset = offset + object.left_brace().width();
+        Frame { members: Members::Pairs(object.pairs()), index: 0, offset, close: object.right_brace().0, keys: HashSet::new() }
+    }
+
+    fn array(array: &Array<'g>, offset: u32) -> Self {
+        let offset = offset + array.left_bracket().width();
+        Frame { members: Members::Items(array.values()), index: 0, offset, close: array.right_bracket().0, keys: HashSet::new() }
+    }
+}
+
+impl Members<'_> {
+    fn len(&self) -> usize {
+        match self {
+            Members::Pairs(pairs) => pairs.len(),
+            Members::Items(items) => items.len(),
+        }
+    }
+}
+
+impl<'a> Collector<'a> {
+    fn push(&mut self, code: ErrorCode, severity: Severity, message: impl Into<Cow<'static, str>>, range: TextRange) {
+        self.raw.push((code, severity, message.into(), range));
+    }
+
+    /// The tree only keeps the code of an error; its message comes from lexing the text again.
+    fn error(&mut self, code: ErrorCode, range: TextRange) {
+        let e = parse::error(code, &self.text[range.start as usize..], self.options);
+        self.push(code, Severity::Error, e.message().to_string(), range);
+    }
+
+    fn policy(&mut self, policy: Policy, code: ErrorCode, message: impl Into<Cow<'static, str>>, range: TextRange) {
+        if let Some(severity) = policy.severity() {
+            self.push(code, severity, message, range);
+        }
+    }
+
+    /// Comments are lexed in every dialect; whether they are allowed is up to the policy. An
+    /// unterminated one is an error either way.
+    fn trivia(&mut self, mut offset: u32, len: u32) {
+        for (kind, token) in trivia::split(&self.text[offset as usize..(offset + len) as usize]) {
+            if kind != Trivia::Whitespace {
+                let range = TextRange::at(offset, token.trim_end().len() as u32);
+                self.policy(self.options.comments, ErrorCode::Comment, "Comments are not allowed", range);
+            }
+            if trivia::is_unterminated(kind, token) {
+                self.push(ErrorCode::UnterminatedComment, Severity::Error, "Unterminated block comment", TextRange::at(offset, 2));
+            }
+            offset += token.len() as u32;
+        }
+    }
+
+    /// Trailing content runs to the end of the input; the diagnostic leaves out trailing whitespace.
+    fn trailing(&mut self, trailing: &Offset<Option<Node<tokens::Invalid>>>, offset: u32) {
+        self.trivia(offset, trailing.0);
+        if let Some(Node(width, tokens::Invalid(code))) = trailing.1 {
+            let start = offset + trailing.0;
+            self.unterminated_comment(start, width);
+            let len = self.text[start as usize..(start + width) as usize].trim_end().len();
+            let e = parse::error(code, &self.text[start as usize..], self.options);
+            self.policy(self.options.trailing_content, code, e.message().to_string(), TextRange::at(start, len as u32));
+        }
+    }
+
+    /// An unterminated block comment in a malformed span swallows the rest of the input; this points
+    /// at where the comment starts.
+    fn unterminated_comment(&mut self, offset: u32, len: u32) {
+        let span = &self.text[offset as usize..(offset + len) as usize];
+        if let Some(start) = trivia::unterminated_comment(span, self.options.json5()) {
+            self.push(ErrorCode::UnterminatedComment, Severity::Error, "Unterminated block comment", TextRange::at(offset + start, 2));
+        }
+    }
+
+    /// Malformed strings only carry a flag in the tree; the details come from scanning them again.
+    fn string(&mut self, s: &tokens::String, offset: u32) {
+        if s.malformed {
+            escape::scan(&self.text[offset as usize..], self.options.json5(), |range, code, message| {
+                self.raw.push((code, Severity::Error, message.into(), TextRange::at(offset + range.start, range.len())));
+            });
+        }
+    }
+
+    /// Walks the value with a stack of the containers it is in, so that a deep document cannot
+    /// overflow the call stack.
+    fn value<'g>(&mut self, node: &Node<Value<'g>>, offset: u32) {
+        let mut open: Vec<Frame<'g, 'a>> = Vec::new();
+        let mut next = Some((*node, offset));
+        loop {
+            if let Some((node, offset)) = next {
+                match node.1 {
+                    Value::Invalid(code) => {
+                        self.error(code, TextRange::at(offset, node.width()));
+                        self.unterminated_comment(offset, node.width());
+                    }
+                    Value::String(s) => self.string(&s, offset),
+                    Value::Object(o) => {
+                        if o.right_brace().1.is_none() {
+                            self.error(ErrorCode::UnclosedObject, TextRange::at(offset, 1));
+                        }
+                        open.push(Frame::object(&o, offset));
+                    }
+                    Value::Array(a) => {
+                        if a.right_bracket().1.is_none() {
+                            self.error(ErrorCode::UnclosedArray, TextRange::at(offset, 1));
+                        }
+                        open.push(Frame::array(&a, offset));
+                    }
+                    _ => {}
+                }
+            }
+            let Some(frame) = open.last_mut() else {
+                return;
+            };
+            next = self.member(frame);
+            if next.is_none() {
+                open.pop();
+            }
+        }
+    }
+
+    /// Finishes the member of `frame` whose value was just checked, and checks the next one up to
+    /// its value, which is returned. Checks the end of the container once there are no more.
+    fn member<'g>(&mut self, frame: &mut Frame<'g, 'a>) -> Option<(Node<Value<'g>>, u32)> {
+        let last = frame.index == frame.members.len();
+        if frame.index > 0 {
+            let (value, comma) = match &frame.members {
+                Members::Pairs(pairs) => (pairs[frame.index - 1].value.width(), pairs[frame.index - 1].comma),
+                Members::Items(items) => (items[frame.index - 1].item.width(), items[frame.index - 1].comma),
+            };
+            frame.offset += value;
+            self.trivia(frame.offset, comma.0);
+            self.comma(comma.1.is_some(), frame.offset, comma.0, last);
+            frame.offset += comma.width();
+        }
+        if last {
+            self.trivia(frame.offset, frame.close);
+            return None;
+        }
+        frame.index += 1;
+        let value = match &frame.members {
+            Members::Pairs(pairs) => {
+                let pair = pairs[frame.index - 1];
+                self.key(&pair, frame.offset, &mut frame.keys);
+                frame.offset += pair.key.width();
+                self.trivia(frame.offset, pair.colon.0);
+                frame.offset += pair.colon.width();
+                pair.value
+            }
+            Members::Items(items) => items[frame.index - 1].item,
+        };
+        self.trivia(frame.offset, value.0);
+        Some((value.1, frame.offset + value.0))
+    }
+
+    fn key(&mut self, pair: &Pair, offset: u32, keys: &mut HashSet<Cow<'a, str>>) {
+        let key_start = offset + pair.key.0;
+        let key_end = offset + pair.key.width();
+        let key = &self.text[key_start as usize..key_end as usize];
+        self.trivia(offset, pair.key.0);
+        let decoded = match &pair.key.1 .1 {
+            Key::Invalid(code) => {
+                self.error(*code, TextRange::new(key_start, key_end));
+                None
+            }
+            Key::String(s) => {
+                self.string(s, key_start);
+                Some(escape::unescape(key, self.options.json5()))
+            }
+            Key::Identifier(_) => Some(Cow::Borrowed(key)),
+        };
+        if let Some(decoded) = decoded {
+            if !keys.insert(decoded) {
+                let message = format!("Duplicate key {}", key);
+                self.policy(self.options.duplicate_keys, ErrorCode::DuplicateKey, message, TextRange::new(key_start, key_end));
+            }
+        }
+        if pair.colon.1.is_none() && !matches!(pair.key.1 .1, Key::Invalid(_)) {
+            self.push(ErrorCode::MissingColon, Severity::Error, "Expected ':' after key", TextRange::empty(key_end));
+        }
+    }
+
+    /// `offset` is the end of the member, `trivia` the whitespace between it and the comma.
+    fn comma(&mut self, present: bool, offset: u32, trivia: u32, last: bool) {
+        match (present, last) {
+            (false, false) => {
+                self.push(ErrorCode::MissingComma, Severity::Error, "Expected ',' between members", TextRange::empty(offset));
+            }
+            (true, true) => {
+                self.policy(self.options.trailing_commas, ErrorCode::TrailingComma, "Trailing comma", TextRange::at(offset + trivia, 1));
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::options::Dialect;
+    use crate::parse;
+
+    #[test]
+    fn test_codes_and_ranges() {
+        let s = parse("{\n  \"a\": 01,\n  \"b\" 2\n  \"c\": [1,],\n}".to_string());
+        let d = s.diagnostics();
+        let summary: Vec<_> = d.iter().map(|d| (d.code, d.severity, d.range.start, d.range.end)).collect();
+        assert_eq!(summary, vec![
+            (ErrorCode::LeadingZero, Severity::Error, 9, 11),
+            (ErrorCode::MissingColon, Severity::Error, 18, 18),
+            (ErrorCode::MissingComma, Severity::Error, 20, 20),
+            (ErrorCode::TrailingComma, Severity::Warning, 30, 31),
+            (ErrorCode::TrailingComma, Severity::Warning, 32, 33),
+        ]);
+        assert_eq!(d[0].start, Position { line: 1, column: 7, column_utf16: 7 });
+        assert_eq!(d[2].end, Position { line: 2, column: 7, column_utf16: 7 });
+    }
+
+    #[test]
+    fn test_utf16_columns() {
+        let s = parse("[\"😀é\", tru]".to_string());
+        let d = s.diagnostics();
+        assert_eq!(d.len(), 1);
+        assert_eq!(d[0].code, ErrorCode::InvalidLiteral);
+        assert_eq!(d[0].range, TextRange::new(11, 14));
+        assert_eq!(d[0].start, Position { line: 0, column: 11, column_utf16: 8 });
+    }
+
+    /// Looking up a position does not count the line from its start, which would take quadratic
+    /// time here.
+    #[test]
+    fn test_many_errors_on_one_line() {
+        let n = 50_000;
+        let s = parse(format!("[{}1]", "\"é\", tru, ".repeat(n)));
+        let d = s.diagnostics();
+        assert_eq!(d.len(), n);
+        let last = (n - 1) as u32;
+        assert_eq!(d[n - 1].start, Position { line: 0, column: 11 * last + 7, column_utf16: 10 * last + 6 });
+    }
+
+    #[test]
+    fn test_escape_diagnostics() {
+        let s = parse("{\"k\\q\": \"a\\x b \\uD800\", \"ok\": \"\\u00e9\"}".to_string());
+        assert!(s.has_error());
+        let d: Vec<_> = s.diagnostics().iter().map(|d| (d.code, d.range.start, d.range.end)).collect();
+        assert_eq!(d, vec![
+            (ErrorCode::InvalidEscape, 3, 5),
+            (ErrorCode::InvalidEscape, 10, 12),
+            (ErrorCode::LoneSurrogate, 15, 21),
+        ]);
+    }
+
+    #[test]
+    fn test_dialects() {
+        let text = "{\"a\": [1,], // note\n \"b\": 2,}";
+        let codes = |dialect: Dialect| -> Vec<_> {
+            let s = crate::parse_with(text.to_string(), dialect.into());
+            s.diagnostics().iter().map(|d| (d.code, d.severity, d.range.start, d.range.end)).collect()
+        };
+        assert_eq!(codes(Dialect::Json), vec![
+            (ErrorCode::TrailingComma, Severity::Error, 8, 9),
+            (ErrorCode::Comment, Severity::Error, 12, 19),
+            (ErrorCode::TrailingComma, Severity::Error, 27, 28),
+        ]);
+        assert_eq!(codes(Dialect::Jsonc), vec![
+            (ErrorCode::TrailingComma, Severity::Warning, 8, 9),
+            (ErrorCode::TrailingComma, Severity::Warning, 27, 28),
+        ]);
+        assert_eq!(codes(Dialect::Json5), vec![]);
+    }
+
+    #[test]
+    fn test_policies() {
+        let text = "{\"a\": 1, \"\\u0061\": [2,], b: 3} /* end */ 4";
+        let at = |needle: &str| text.find(needle).unwrap() as u32;
+        let key = (at("\"\\u"), at("\"\\u") + 8);
+        let comma = at(",]");
+        let comment = (at("/*"), at("/*") + 9);
+        let tail = text.len() as u32 - 1;
+        let codes = |options: ParseOptions| -> Vec<_> {
+            let s = crate::parse_with(text.to_string(), options);
+            s.diagnostics().iter().map(|d| (d.code, d.severity, d.range.start, d.range.end)).collect()
+        };
+        let json5 = ParseOptions::new(Dialect::Json5);
+        assert_eq!(codes(json5), vec![
+            (ErrorCode::DuplicateKey, Severity::Warning, key.0, key.1),
+            (ErrorCode::TrailingContent, Severity::Error, tail, tail + 1),
+        ]);
+        let strict = ParseOptions { comments: Policy::Error, trailing_commas: Policy::Warn, duplicate_keys: Policy::Error, ..json5 };
+        assert_eq!(codes(strict), vec![
+            (ErrorCode::DuplicateKey, Severity::Error, key.0, key.1),
+            (ErrorCode::TrailingComma, Severity::Warning, comma, comma + 1),
+            (ErrorCode::Comment, Severity::Error, comment.0, comment.1),
+            (ErrorCode::TrailingContent, Severity::Error, tail, tail + 1),
+        ]);
+        let lenient = ParseOptions { duplicate_keys: Policy::Allow, trailing_content: Policy::Warn, ..json5 };
+        assert_eq!(codes(lenient), vec![(ErrorCode::TrailingContent, Severity::Warning, tail, tail + 1)]);
+
+        assert!(crate::parse_with(text.to_string(), strict).has_error());
+        assert!(!crate::parse_with(text.to_string(), lenient).has_error());
+    }
+
+    #[test]
+    fn test_block_comments() {
+        let s = parse("/* header */ {\"a\": /* inline */ 1, /* x */ \"b\": [/**/2]}".to_string());
+        assert!(!s.has_error());
+        assert!(s.diagnostics().is_empty());
+
+        let s = parse("{\"a\": 1 /* oops }".to_string());
+        assert!(s.has_error());
+        let d: Vec<_> = s.diagnostics().iter().map(|d| (d.code, d.range.start, d.range.end)).collect();
+        assert_eq!(d, vec![(ErrorCode::UnclosedObject, 0, 1), (ErrorCode::UnterminatedComment, 8, 10)]);
+
+        let s = parse("/* nothing".to_string());
+        let d: Vec<_> = s.diagnostics().iter().map(|d| d.code).collect();
+        assert_eq!(d, vec![ErrorCode::UnterminatedComment, ErrorCode::ExpectedValue]);
+    }
+}
@@ -0,0 +1,212 @@
+use ropey::Rope;
+use crate::buffer::Buffer;
+use crate::diagnostic;
+use crate::options::ParseOptions;
+use crate::green::{GreenElement, GreenNode};
+use crate::parse::Parser;
+use crate::range::TextRange;
+use crate::source::Source;
+use crate::syntax::SyntaxKind;
+use crate::width::Width;
+
+/// A document that is edited in place, e.g. on every keystroke in an editor.
+///
+/// The text lives in a [`Rope`], and an edit only reparses the innermost object or array that
+/// strictly contains it. Because widths in the tree are relative, the rest of the tree is reused
+/// as-is and only the nodes enclosing the edit are copied.
+#[derive(Debug, Clone)]
+pub struct Document {
+    rope: Rope,
+    dom: GreenNode,
+    options: ParseOptions,
+}
+
+impl Document {
+    pub fn new(text: &str) -> Self {
+        Self::with_options(text, ParseOptions::default())
+    }
+
+    pub fn with_options(text: &str, options: ParseOptions) -> Self {
+        let dom = Parser::new(Buffer::new(text, options)).root();
+        Self { rope: Rope::from_str(text), dom, options }
+    }
+
+    pub fn rope(&self) -> &Rope {
+        &self.rope
+    }
+
+    pub fn text(&self) -> String {
+        self.rope.to_string()
+    }
+
+    /// Whether the document failed to parse or breaks a policy that is set to [`Policy::Error`],
+    /// exactly like [`Source::has_error`].
+    pub fn has_error(&self) -> bool {
+        diagnostic::has_error(&self.text(), &self.dom, self.options)
+    }
+
+    /// Copies the current state into a [`Source`], for the APIs that work on a flat string.
+    pub fn to_source(&self) -> Source {
+        Source { content: self.text(), dom: self.dom.clone(), options: self.options }
+    }
+
+    /// Replaces the byte `range` of the current text with `text`, and returns the range of the new
+    /// text that was reparsed.
+    pub fn apply_edit(&mut self, range: TextRange, text: &str) -> TextRange {
+        let start = self.rope.byte_to_char(range.start as usize);
+        let end = self.rope.byte_to_char(range.end as usize);
+        self.rope.remove(start..end);
+        self.rope.insert(start, text);
+        let delta = text.len() as i64 - range.len() as i64;
+
+        let mut candidates = Vec::new();
+        let root = self.dom.children().iter().position(|c| !c.kind().is_trivia()).expect("the root has a value");
+        let offset = self.dom.children()[..root].iter().map(Width::width).sum();
+        containing(&self.dom.children()[root], offset, range, &mut vec![root], &mut candidates);
+        for (path, start, width) in candidates.into_iter().rev() {
+            let width = (width as i64 + delta) as u32;
+            let slice = self.rope.byte_slice(start as usize..(start + width) as usize).to_string();
+            // Every container on the way down counts towards the nesting limit, and their closing
+            // brackets end an unclosed container inside them.
+            let mut enclosing = Vec::new();
+            let mut parent = &self.dom;
+            for &i in &path[..path.len() - 1] {
+                parent = parent.children()[i].as_node().expect("paths only lead through nodes");
+                if matches!(parent.kind(), SyntaxKind::Object | SyntaxKind::Array) {
+                    enclosing.push(parent.kind());
+                }
+            }
+            let node = Parser::nested(Buffer::new(&slice, self.options), &enclosing).parse_value();
+            // The container must still close exactly where the edited one did, with its own bracket.
+            // If it now closes earlier or not at all, the change leaks into the parent, so try one
+            // level up.
+            let closed = node.as_node().and_then(|n| n.children().last()).map(GreenElement::kind);
+            if node.width() == width && matches!(closed, Some(SyntaxKind::RightBrace | SyntaxKind::RightBracket)) {
+                self.dom = replace(&self.dom, &path, node);
+                return TextRange::at(start, width);
+            }
+        }
+        *self = Document::with_options(&self.text(), self.options);
+        TextRange::new(0, self.rope.len_bytes() as u32)
+    }
+}
+
+/// Collects the containers whose contents strictly contain `range` (i.e. the edit does not touch
+/// their brackets), outermost first, as (path of child indices from the root, start, width).
+fn containing(value: &GreenElement, start: u32, range: TextRange, path: &mut Vec<usize>, out: &mut Vec<(Vec<usize>, u32, u32)>) {
+    let Some(node) = value.as_node() else {
+        return;
+    };
+    if !(start < range.start && range.end < start + value.width()) {
+        return;
+    }
+    out.push((path.clone(), start, value.width()));
+    let mut offset = start;
+    for (i, member) in node.children().iter().enumerate() {
+        if offset > range.end {
+            break;
+        }
+        if let Some(member) = member.as_node() {
+            // The value is the last child of the member apart from trivia and the comma.
+            let (j, _) = member
+                .children()
+                .iter()
+                .enumerate()
+                .rfind(|(_, c)| !c.kind().is_trivia() && c.kind() != SyntaxKind::Comma)
+                .expect("a member has a value");
+            let value_start = offset + member.children()[..j].iter().map(Width::width).sum::<u32>();
+            path.extend([i, j]);
+            containing(&member.children()[j], value_start, range, path, out);
+            path.truncate(path.len() - 2);
+        }
+        offset += member.width();
+    }
+}
+
+/// Swaps in the reparsed node at `path`. Every node on the way is copied, which recomputes its
+/// width; everything else is shared with the old tree.
+fn replace(node: &GreenNode, path: &[usize], element: GreenElement) -> GreenNode {
+    let (&i, rest) = path.split_first().expect("the path is not empty");
+    let child = match rest {
+        [] => element,
+        _ => GreenElement::Node(replace(node.children()[i].as_node().expect("paths only lead through nodes"), rest, element)),
+    };
+    node.replace_child(i, child)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::options::Policy;
+    use crate::parse;
+    use crate::test_support::Rng;
+
+    #[test]
+    fn test_reparses_innermost_container() {
+        let text = include_str!("../tests/data/multi.json");
+        let mut doc = Document::new(text);
+        // Inside `{ "e": "f" }` in the array under "d".
+        let at = text.find("\"f\" }").unwrap() as u32;
+        let reparsed = doc.apply_edit(TextRange::new(at, at + 3), "[1, 2]");
+        assert_eq!(&doc.text()[reparsed.start as usize..reparsed.end as usize], "{ \"e\": [1, 2] }");
+        assert_eq!(doc.dom, parse(doc.text()).dom);
+    }
+
+    #[test]
+    fn test_falls_back_when_brackets_change() {
+        let mut doc = Document::new("{\"a\": [1, 2], \"b\": 3}");
+        // Deleting the `]` makes the array swallow the rest of the object.
+        let reparsed = doc.apply_edit(TextRange::new(11, 12), "");
+        assert_eq!(reparsed, TextRange::new(0, 20));
+        assert_eq!(doc.dom, parse(doc.text()).dom);
+        // Inserting a `]` into the array closes it early, so the object is reparsed instead.
+        let mut doc = Document::new("{\"a\": [1, 2], \"b\": 3}");
+        let reparsed = doc.apply_edit(TextRange::empty(8), "]");
+        assert_eq!(reparsed, TextRange::new(0, 22));
+        assert_eq!(doc.dom, parse(doc.text()).dom);
+    }
+
+    #[test]
+    fn test_has_error_follows_policies() {
+        use crate::options::Dialect;
+        let strict = ParseOptions { duplicate_keys: Policy::Error, ..ParseOptions::new(Dialect::Jsonc) };
+        for (text, options) in [
+            ("[1,]", Dialect::Json.into()),
+            ("[1] // c", Dialect::Json.into()),
+            ("{\"a\": 1, \"a\": 2}", strict),
+            ("[1] x", Dialect::Jsonc.into()),
+        ] {
+            let doc = Document::with_options(text, options);
+            assert!(doc.has_error(), "{:?}", text);
+            assert!(doc.to_source().has_error(), "{:?}", text);
+        }
+        assert!(!Document::with_options("[1,] // c", Dialect::Jsonc.into()).has_error());
+    }
+
+    /// Applies a deterministic sequence of pseudo-random edits and checks after every one that the
+    /// incrementally updated tree is identical to a full reparse.
+    #[test]
+    fn test_matches_full_reparse() {
+        const SNIPPETS: &[&str] = &[
+            "", "1", "-", ",", ":", " ", "\n", "\"", "\"k\"", "{", "}", "[", "]", "tru", "true", "\"a\": 2,", "[3, {}]", "é",
+        ];
+        let mut rng = Rng::new(0x2545_f491_4f6c_dd1d);
+        for base in [include_str!("../tests/data/multi.json"), "[[1, [2, [3]]], {\"a\": {\"b\": {}}}]"] {
+            // Random edits quickly unbalance the brackets, so start over regularly to keep
+            // exercising the incremental path.
+            for _ in 0..100 {
+                let mut doc = Document::new(base);
+                let mut text = base.to_string();
+                for _ in 0..5 {
+                    let range = rng.range(&text);
+                    let insert = rng.pick(SNIPPETS);
+                    doc.apply_edit(TextRange::new(range.start as u32, range.end as u32), insert);
+                    text.replace_range(range, insert);
+                    assert_eq!(doc.text(), text);
+                    assert_eq!(doc.dom, parse(text.clone()).dom, "after editing {:?}", text);
+                    assert_eq!(doc.has_error(), parse(text.clone()).has_error());
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,465 @@
+use crate::ast::{Array, Object};
+use crate::options::{ParseOptions, Policy};
+use crate::pointer;
+use crate::range::TextRange;
+use crate::source::Source;
+use crate::syntax::SyntaxNode;
+use crate::tokens::Trivia;
+use crate::trivia;
+
+/// A replacement of `range` in the original text by `new_text`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextEdit {
+    pub range: TextRange,
+    pub new_text: String,
+}
+
+impl TextEdit {
+    pub fn insert(offset: u32, text: impl Into<String>) -> Self {
+        Self { range: TextRange::empty(offset), new_text: text.into() }
+    }
+
+    pub fn delete(range: TextRange) -> Self {
+        Self { range, new_text: String::new() }
+    }
+
+    pub fn replace(range: TextRange, text: impl Into<String>) -> Self {
+        Self { range, new_text: text.into() }
+    }
+}
+
+/// Applies non-overlapping edits, all expressed against `text`, in any order.
+pub fn apply_edits(text: &str, edits: &[TextEdit]) -> String {
+    let mut edits: Vec<&TextEdit> = edits.iter().collect();
+    edits.sort_by_key(|e| (e.range.start, e.range.end));
+    let mut result = String::with_capacity(text.len());
+    let mut offset = 0;
+    for edit in edits {
+        result.push_str(&text[offset..edit.range.start as usize]);
+        result.push_str(&edit.new_text);
+        offset = edit.range.end as usize;
+    }
+    result.push_str(&text[offset..]);
+    result
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EditError {
+    /// The pointer is not a valid RFC 6901 JSON pointer.
+    InvalidPointer,
+    NotFound,
+    NotAnObject,
+    NotAnArray,
+    DuplicateKey,
+    /// The text to insert is not a single well-formed JSON value.
+    InvalidValue,
+}
+
+impl std::fmt::Display for EditError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            EditError::InvalidPointer => "invalid JSON pointer",
+            EditError::NotFound => "no value at pointer",
+            EditError::NotAnObject => "value at pointer is not an object",
+            EditError::NotAnArray => "value at pointer is not an array",
+            EditError::DuplicateKey => "object already contains key",
+            EditError::InvalidValue => "value is not valid JSON",
+        })
+    }
+}
+
+impl std::error::Error for EditError {}
+
+/// Every method computes the minimal edits against the current text, applies them, reparses, and
+/// returns the edits so they can be forwarded to an editor. Bytes outside the edits are untouched,
+/// so comments and formatting elsewhere survive.
+impl Source {
+    /// Replaces the value at `pointer`, or adds it if the pointer names a missing key of an existing
+    /// object or `-` (the end) of an existing array.
+    pub fn set(&mut self, pointer: &str, value: &str) -> Result<Vec<TextEdit>, EditError> {
+        check_value(value, self.options)?;
+        let mut tokens = pointer::tokens(pointer).ok_or(EditError::InvalidPointer)?;
+        let edits = match pointer::walk(self.root(), &tokens) {
+            Some(node) => vec![TextEdit::replace(node.text_range(), value)],
+            None => {
+                let last = tokens.pop().ok_or(EditError::NotFound)?;
+                let parent = pointer::walk(self.root(), &tokens).ok_or(EditError::NotFound)?;
+                if let Some(object) = Object::cast(parent.clone()) {
+                    let pair = format!("{}: {}", quote(&last), value);
+                    insert_member(&self.content, object.syntax(), &members(&object), pair)
+                } else if let (Some(array), "-") = (Array::cast(parent), last.as_str()) {
+                    insert_member(&self.content, array.syntax(), &items(&array), value.to_string())
+                } else {
+                    return Err(EditError::NotFound);
+                }
+            }
+        };
+        self.apply(edits)
+    }
+
+    /// Appends `"key": value` to the object at `pointer`.
+    pub fn insert_pair(&mut self, pointer: &str, key: &str, value: &str) -> Result<Vec<TextEdit>, EditError> {
+        check_value(value, self.options)?;
+        let object = self.object(pointer)?;
+        if object.get(key).is_some() {
+            return Err(EditError::DuplicateKey);
+        }
+        let pair = format!("{}: {}", quote(key), value);
+        let edits = insert_member(&self.content, object.syntax(), &members(&object), pair);
+        self.apply(edits)
+    }
+
+    /// Removes the pair with `key` from the object at `pointer`, together with the comment and
+    /// whitespace preceding it.
+    pub fn remove_pair(&mut self, pointer: &str, key: &str) -> Result<Vec<TextEdit>, EditError> {
+        let object = self.object(pointer)?;
+        let index = object.pairs().position(|p| p.key_text().as_deref() == Some(key)).ok_or(EditError::NotFound)?;
+        let edits = remove_member(&self.content, object.syntax(), &members(&object), index);
+        self.apply(edits)
+    }
+
+    /// Appends `value` to the array at `pointer`.
+    pub fn push_item(&mut self, pointer: &str, value: &str) -> Result<Vec<TextEdit>, EditError> {
+        check_value(value, self.options)?;
+        let array = self.array(pointer)?;
+        let edits = insert_member(&self.content, array.syntax(), &items(&array), value.to_string());
+        self.apply(edits)
+    }
+
+    /// Removes the item at `index` from the array at `pointer`, together with the comment and
+    /// whitespace preceding it.
+    pub fn remove_item(&mut self, pointer: &str, index: usize) -> Result<Vec<TextEdit>, EditError> {
+        let array = self.array(pointer)?;
+        let items = items(&array);
+        if index >= items.len() {
+            return Err(EditError::NotFound);
+        }
+        let edits = remove_member(&self.content, array.syntax(), &items, index);
+        self.apply(edits)
+    }
+
+    fn object(&self, pointer: &str) -> Result<Object<'_>, EditError> {
+        let tokens = pointer::tokens(pointer).ok_or(EditError::InvalidPointer)?;
+        let node = pointer::walk(self.root(), &tokens).ok_or(EditError::NotFound)?;
+        Object::cast(node).ok_or(EditError::NotAnObject)
+    }
+
+    fn array(&self, pointer: &str) -> Result<Array<'_>, EditError> {
+        let tokens = pointer::tokens(pointer).ok_or(EditError::InvalidPointer)?;
+        let node = pointer::walk(self.root(), &tokens).ok_or(EditError::NotFound)?;
+        Array::cast(node).ok_or(EditError::NotAnArray)
+    }
+
+    /// Applies `edits` unless they break a document that had no errors, e.g. by inserting a value
+    /// that is only well-formed on its own.
+    fn apply(&mut self, edits: Vec<TextEdit>) -> Result<Vec<TextEdit>, EditError> {
+        let edited = crate::parse_with(apply_edits(&self.content, &edits), self.options);
+        if edited.has_error() && !self.has_error() {
+            return Err(EditError::InvalidValue);
+        }
+        *self = edited;
+        Ok(edits)
+    }
+}
+
+/// Checks that `value` is a single value with nothing around it. A comment in or after the value
+/// could swallow or detach from the text around the place it is inserted at.
+fn check_value(value: &str, options: ParseOptions) -> Result<(), EditError> {
+    let parsed = crate::parse_with(value.to_string(), ParseOptions { comments: Policy::Error, ..options });
+    if parsed.has_error() || value.trim() != value {
+        return Err(EditError::InvalidValue);
+    }
+    Ok(())
+}
+
+/// The parts of a pair or item that matter for comma handling.
+struct Member {
+    range: TextRange,
+    value_end: u32,
+}
+
+impl Member {
+    fn has_comma(&self) -> bool {
+        self.range.end > self.value_end
+    }
+}
+
+fn members(object: &Object<'_>) -> Vec<Member> {
+    object
+        .pairs()
+        .map(|p| Member {
+            range: p.syntax().text_range(),
+            value_end: p.value().map(|v| v.text_range().end).unwrap_or(p.syntax().text_range().end),
+        })
+        .collect()
+}
+
+fn items(array: &Array<'_>) -> Vec<Member> {
+    array
+        .items()
+        .map(|i| Member {
+            range: i.syntax().text_range(),
+            value_end: i.value().map(|v| v.text_range().end).unwrap_or(i.syntax().text_range().end),
+        })
+        .collect()
+}
+
+/// Appends `member` after the last member of `container`, separated the same way the last member is
+/// separated from what precedes it. A trailing comma on the last member is kept trailing, and
+/// comments at the end of the last member's line stay with it.
+fn insert_member(text: &str, container: &SyntaxNode<'_>, members: &[Member], member: String) -> Vec<TextEdit> {
+    let Some(last) = members.last() else {
+        let range = container.text_range();
+        let open = range.start + 1;
+        // An unclosed container has no closing bracket to leave out.
+        let closed = range.len() > 1 && container.text().ends_with(['}', ']']);
+        let interior = &text[open as usize..(range.end - closed as u32) as usize];
+        if !interior.contains('\n') {
+            return vec![TextEdit::insert(open, member)];
+        }
+        let indent = format!("{}{}", line_indent(text, range.start), indent_unit(text));
+        return vec![TextEdit::insert(open, format!("{}{}{}", line_ending(text), indent, member))];
+    };
+    let end = comments_end(text, last.range.end);
+    let separator = match starts_line(text, last.range.start) || end > last.range.end {
+        true => format!("{}{}", line_ending(text), line_indent(text, last.range.start)),
+        false => " ".to_string(),
+    };
+    if last.has_comma() {
+        vec![TextEdit::insert(end, format!("{}{},", separator, member))]
+    } else if end > last.range.end {
+        vec![TextEdit::insert(last.range.end, ","), TextEdit::insert(end, format!("{}{}", separator, member))]
+    } else {
+        vec![TextEdit::insert(end, format!(",{}{}", separator, member))]
+    }
+}
+
+/// Deletes a member with the trivia before it and the comments after it on the same line.
+/// Removing the last member also removes the comma before it, unless the member itself had a
+/// trailing comma; the comments after that comma stay. A next member on the same line moves up to
+/// where the first one started.
+fn remove_member(text: &str, container: &SyntaxNode<'_>, members: &[Member], index: usize) -> Vec<TextEdit> {
+    let member = &members[index];
+    let end = comments_end(text, member.range.end);
+    match index {
+        0 => {
+            let start = comments_end(text, container.text_range().start + 1);
+            let between = |from: u32, to: u32| &text[from as usize..to as usize];
+            match members.get(1) {
+                Some(next) if between(end, next.range.start).trim_start_matches([' ', '\t']).is_empty() => {
+                    let start = match between(start, member.range.start).trim().is_empty() {
+                        true => member.range.start,
+                        false => start,
+                    };
+                    vec![TextEdit::delete(TextRange::new(start, next.range.start))]
+                }
+                _ => vec![TextEdit::delete(TextRange::new(start, end))],
+            }
+        }
+        i => {
+            let previous = &members[i - 1];
+            let start = comments_end(text, previous.range.end);
+            let mut edits = vec![TextEdit::delete(TextRange::new(start, end))];
+            if i + 1 == members.len() && !member.has_comma() && previous.has_comma() {
+                edits.insert(0, TextEdit::delete(TextRange::new(previous.range.end - 1, previous.range.end)));
+            }
+            edits
+        }
+    }
+}
+
+/// Where the comments after `offset` on its line end, if nothing else follows them on that line.
+/// Otherwise, and if there are none, `offset`.
+fn comments_end(text: &str, offset: u32) -> u32 {
+    let mut end = offset;
+    let mut position = offset;
+    for (kind, token) in trivia::split(&text[offset as usize..]) {
+        match (kind, token.contains('\n')) {
+            (Trivia::Whitespace, true) => return end,
+            (Trivia::Whitespace, false) => {}
+            (Trivia::LineComment, _) => return position + token.trim_end_matches(['\n', '\r']).len() as u32,
+            (Trivia::BlockComment, true) => return offset,
+            (Trivia::BlockComment, false) => end = position + token.len() as u32,
+        }
+        position += token.len() as u32;
+    }
+    if position as usize == text.len() {
+        end
+    } else {
+        offset
+    }
+}
+
+/// The line ending of the first line, or `\n`.
+pub(crate) fn line_ending(text: &str) -> &'static str {
+    match text.find('\n') {
+        Some(i) if text[..i].ends_with('\r') => "\r\n",
+        _ => "\n",
+    }
+}
+
+pub(crate) fn line_start(text: &str, offset: u32) -> usize {
+    text[..offset as usize].rfind('\n').map(|i| i + 1).unwrap_or(0)
+}
+
+pub(crate) fn starts_line(text: &str, offset: u32) -> bool {
+    text[line_start(text, offset)..offset as usize].chars().all(char::is_whitespace)
+}
+
+pub(crate) fn line_indent(text: &str, offset: u32) -> &str {
+    let line = &text[line_start(text, offset)..];
+    &line[..line.len() - line.trim_start_matches([' ', '\t']).len()]
+}
+
+/// The indentation of the first indented line, or two spaces.
+pub(crate) fn indent_unit(text: &str) -> &str {
+    text.lines()
+        .map(|line| &line[..line.len() - line.trim_start_matches([' ', '\t']).len()])
+        .find(|indent| !indent.is_empty())
+        .unwrap_or("  ")
+}
+
+pub(crate) fn quote(key: &str) -> String {
+    let mut s = String::with_capacity(key.len() + 2);
+    s.push('"');
+    for c in key.chars() {
+        match c {
+            '"' => s.push_str("\\\""),
+            '\\' => s.push_str("\\\\"),
+            '\n' => s.push_str("\\n"),
+            '\r' => s.push_str("\\r"),
+            '\t' => s.push_str("\\t"),
+            c if c.is_control() => s.push_str(&format!("\\u{:04x}", c as u32)),
+            c => s.push(c),
+        }
+    }
+    s.push('"');
+    s
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+
+    const CONFIG: &str = "{\n  // the name\n  \"name\": \"lst\", // inline\n  \"tags\": [\"a\", \"b\"],\n  \"nested\": {}\n}\n";
+
+    #[test]
+    fn test_set_replaces_only_the_value() {
+        let mut s = parse(CONFIG.to_string());
+        let edits = s.set("/name", "\"json\"").unwrap();
+        assert_eq!(edits, vec![TextEdit::replace(TextRange::new(26, 31), "\"json\"")]);
+        assert_eq!(s.content, CONFIG.replace("\"lst\"", "\"json\""));
+    }
+
+    #[test]
+    fn test_set_inserts_missing_key() {
+        let mut s = parse(CONFIG.to_string());
+        s.set("/version", "1").unwrap();
+        assert_eq!(s.content, CONFIG.replace("{}\n}", "{},\n  \"version\": 1\n}"));
+        s.set("/tags/-", "\"c\"").unwrap();
+        assert!(s.content.contains("[\"a\", \"b\", \"c\"]"));
+        assert_eq!(s.set("/missing/key", "1"), Err(EditError::NotFound));
+        assert_eq!(s.set("/name", "{"), Err(EditError::InvalidValue));
+        assert_eq!(s.set("/name", "2 // x"), Err(EditError::InvalidValue));
+        assert_eq!(s.set("/name", " 2"), Err(EditError::InvalidValue));
+        assert_eq!(s.set("/name", "[/* x */]"), Err(EditError::InvalidValue));
+        assert!(!s.has_error());
+    }
+
+    #[test]
+    fn test_insert_pair_into_empty_objects() {
+        let mut s = parse(CONFIG.to_string());
+        s.insert_pair("/nested", "k", "true").unwrap();
+        assert!(s.content.contains("\"nested\": {\"k\": true}"));
+
+        let mut s = parse("{\n  \"a\": {\n  }\n}".to_string());
+        s.insert_pair("/a", "b", "1").unwrap();
+        assert_eq!(s.content, "{\n  \"a\": {\n    \"b\": 1\n  }\n}");
+        assert_eq!(s.insert_pair("/a", "b", "2"), Err(EditError::DuplicateKey));
+
+        let mut s = parse("{\"a\": [".to_string());
+        s.push_item("/a", "1").unwrap();
+        assert_eq!(s.content, "{\"a\": [1");
+    }
+
+    #[test]
+    fn test_insert_keeps_trailing_comma() {
+        let mut s = parse("[\n\t1,\n\t2,\n]".to_string());
+        s.push_item("", "3").unwrap();
+        assert_eq!(s.content, "[\n\t1,\n\t2,\n\t3,\n]");
+    }
+
+    #[test]
+    fn test_insert_after_trailing_comment() {
+        let mut s = parse("{\n  \"a\": 1 // note\n}".to_string());
+        s.insert_pair("", "b", "2").unwrap();
+        assert_eq!(s.content, "{\n  \"a\": 1, // note\n  \"b\": 2\n}");
+        s.insert_pair("", "c", "3").unwrap();
+        assert_eq!(s.content, "{\n  \"a\": 1, // note\n  \"b\": 2,\n  \"c\": 3\n}");
+        let mut s = parse("[1, /* one */\n 2, /* two */\n]".to_string());
+        s.push_item("", "3").unwrap();
+        assert_eq!(s.content, "[1, /* one */\n 2, /* two */\n 3,\n]");
+    }
+
+    #[test]
+    fn test_remove_pair() {
+        let mut s = parse(CONFIG.to_string());
+        s.remove_pair("", "name").unwrap();
+        assert_eq!(s.content, "{\n  \"tags\": [\"a\", \"b\"],\n  \"nested\": {}\n}\n");
+        s.remove_pair("", "nested").unwrap();
+        assert_eq!(s.content, "{\n  \"tags\": [\"a\", \"b\"]\n}\n");
+        assert!(!s.has_error());
+
+        // The comments at the end of the line before the removed pair stay.
+        let mut s = parse("{\"a\": 1, // keep me\n \"b\": 2 // drop me\n}".to_string());
+        s.remove_pair("", "b").unwrap();
+        assert_eq!(s.content, "{\"a\": 1 // keep me\n}");
+        let mut s = parse("{ // keep me\n  \"a\": 1, // drop me\n  \"b\": 2, /* keep me */\n  \"c\": 3\n}".to_string());
+        s.remove_pair("", "a").unwrap();
+        s.remove_pair("", "c").unwrap();
+        assert_eq!(s.content, "{ // keep me\n  \"b\": 2 /* keep me */\n}");
+    }
+
+    #[test]
+    fn test_remove_item() {
+        let mut s = parse("[1, 2, 3]".to_string());
+        s.remove_item("", 1).unwrap();
+        assert_eq!(s.content, "[1, 3]");
+        s.remove_item("", 1).unwrap();
+        assert_eq!(s.content, "[1]");
+        s.remove_item("", 0).unwrap();
+        assert_eq!(s.content, "[]");
+        assert_eq!(s.remove_item("", 0), Err(EditError::NotFound));
+    }
+
+    /// The next member takes the place of a removed first member, separator and all.
+    #[test]
+    fn test_remove_first_member() {
+        let mut s = parse("{\"a\": 1, \"b\": 2}".to_string());
+        s.remove_pair("", "a").unwrap();
+        assert_eq!(s.content, "{\"b\": 2}");
+        let mut s = parse("[1 /* one */, 2]".to_string());
+        s.remove_item("", 0).unwrap();
+        assert_eq!(s.content, "[2]");
+        let mut s = parse("[ 1, 2 ]".to_string());
+        s.remove_item("", 0).unwrap();
+        assert_eq!(s.content, "[ 2 ]");
+        let mut s = parse("[\n  1, 2\n]".to_string());
+        s.remove_item("", 0).unwrap();
+        assert_eq!(s.content, "[\n  2\n]");
+        let mut s = parse("[1, /* two */ 2]".to_string());
+        s.remove_item("", 0).unwrap();
+        assert_eq!(s.content, "[ /* two */ 2]");
+    }
+
+    #[test]
+    fn test_insert_keeps_line_endings() {
+        let mut s = parse("{\r\n  \"a\": 1\r\n}".to_string());
+        s.insert_pair("", "b", "2").unwrap();
+        assert_eq!(s.content, "{\r\n  \"a\": 1,\r\n  \"b\": 2\r\n}");
+        let mut s = parse("{\r\n  \"a\": {\r\n  }\r\n}".to_string());
+        s.insert_pair("/a", "b", "1").unwrap();
+        assert_eq!(s.content, "{\r\n  \"a\": {\r\n    \"b\": 1\r\n  }\r\n}");
+    }
+}
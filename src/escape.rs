@@ -0,0 +1,193 @@
+use std::borrow::Cow;
+use crate::parse::ErrorCode;
+use crate::range::TextRange;
+
+/// Scans a string literal that starts with its quote at the beginning of `content`, reporting every
+/// malformed escape or raw control character (with its range relative to `content`) to `on_error`.
+/// Returns the length of the literal including both quotes, or `None` if it is not closed before
+/// the end of the line. With `json5`, the literal may be single-quoted and the JSON5 escapes apply.
+pub(crate) fn scan(content: &str, json5: bool, mut on_error: impl FnMut(TextRange, ErrorCode, &'static str)) -> Option<u32> {
+    let bytes = content.as_bytes();
+    let quote = *bytes.first()?;
+    let mut i = 1;
+    while i < bytes.len() {
+        match bytes[i] {
+            c if c == quote => return Some(i as u32 + 1),
+            b'\n' => return None,
+            b'\r' if json5 => return None,
+            b'\\' => i += escape(&content[i..], i, json5, &mut on_error),
+            c if c < 0x20 && !json5 => {
+                on_error(TextRange::at(i as u32, 1), ErrorCode::ControlCharacter, "Control characters must be escaped");
+                i += 1;
+            }
+            _ => i += 1,
+        }
+    }
+    None
+}
+
+/// Validates the escape sequence at the start of `rest`, which is at `at` in the literal, and
+/// returns its length. In JSON a newline is never consumed, so that the caller sees the string end
+/// there; in JSON5 an escaped newline continues the string on the next line.
+fn escape(rest: &str, at: usize, json5: bool, on_error: &mut impl FnMut(TextRange, ErrorCode, &'static str)) -> usize {
+    let bytes = rest.as_bytes();
+    let mut error = |len: usize, code, message| {
+        on_error(TextRange::at(at as u32, len as u32), code, message);
+        len
+    };
+    match rest[1..].chars().next() {
+        Some('"' | '\\' | '/' | 'b' | 'f' | 'n' | 'r' | 't') => 2,
+        Some('u') => match hex4(&bytes[2..]) {
+            None => error(2 + hex_prefix(&bytes[2..]), ErrorCode::InvalidUnicodeEscape, "Expected four hex digits after \\u"),
+            Some(0xD800..=0xDBFF) => match low_surrogate(&bytes[6..]) {
+                Some(_) => 12,
+                None => error(6, ErrorCode::LoneSurrogate, "High surrogate is not followed by a low surrogate"),
+            },
+            Some(0xDC00..=0xDFFF) => error(6, ErrorCode::LoneSurrogate, "Low surrogate is not preceded by a high surrogate"),
+            Some(_) => 6,
+        },
+        None => error(1, ErrorCode::InvalidEscape, "Expected an escape sequence after \\"),
+        Some(_) if json5 => json5_escape(bytes, &mut error),
+        Some('\n') => error(1, ErrorCode::InvalidEscape, "Expected an escape sequence after \\"),
+        Some(c) => error(1 + c.len_utf8(), ErrorCode::InvalidEscape, "Invalid escape sequence"),
+    }
+}
+
+/// The escapes JSON5 adds on top of JSON's. Any other character escapes to itself.
+fn json5_escape(bytes: &[u8], error: &mut impl FnMut(usize, ErrorCode, &'static str) -> usize) -> usize {
+    match bytes[1] {
+        b'0' if !bytes.get(2).is_some_and(u8::is_ascii_digit) => 2,
+        b'0'..=b'9' => error(2, ErrorCode::InvalidEscape, "Digits cannot be escaped"),
+        b'x' => match hex2(&bytes[2..]) {
+            Some(_) => 4,
+            None => error(2 + hex_prefix(&bytes[2..]).min(2), ErrorCode::InvalidEscape, "Expected two hex digits after \\x"),
+        },
+        b'\r' if bytes.get(2) == Some(&b'\n') => 3,
+        _ => 1 + std::str::from_utf8(&bytes[1..]).ok().and_then(|s| s.chars().next()).map_or(1, char::len_utf8),
+    }
+}
+
+/// The number of leading hex digits, up to four.
+fn hex_prefix(bytes: &[u8]) -> usize {
+    bytes.iter().take(4).take_while(|b| b.is_ascii_hexdigit()).count()
+}
+
+/// Parses a `\uXXXX` low surrogate at the start of `bytes`.
+fn low_surrogate(bytes: &[u8]) -> Option<u32> {
+    bytes.strip_prefix(b"\\u").and_then(hex4).filter(|c| (0xDC00..=0xDFFF).contains(c))
+}
+
+fn hex2(bytes: &[u8]) -> Option<u32> {
+    let digits = bytes.get(..2)?;
+    digits.iter().try_fold(0, |acc, &b| Some(acc * 16 + (b as char).to_digit(16)?))
+}
+
+fn hex4(bytes: &[u8]) -> Option<u32> {
+    let digits = bytes.get(..4)?;
+    digits.iter().try_fold(0, |acc, &b| Some(acc * 16 + (b as char).to_digit(16)?))
+}
+
+/// Decodes a string literal, including its quotes. Only allocates if there is an escape; malformed
+/// escapes decode to U+FFFD.
+pub(crate) fn unescape(literal: &str, json5: bool) -> Cow<'_, str> {
+    let quote = if json5 && literal.starts_with('\'') { '\'' } else { '"' };
+    let inner = literal.strip_prefix(quote).unwrap_or(literal);
+    let inner = inner.strip_suffix(quote).unwrap_or(inner);
+    if !inner.contains('\\') {
+        return Cow::Borrowed(inner);
+    }
+    let mut result = String::with_capacity(inner.len());
+    let mut rest = inner;
+    while let Some(i) = rest.find('\\') {
+        result.push_str(&rest[..i]);
+        rest = &rest[i..];
+        let bytes = rest.as_bytes();
+        let (c, len) = match bytes.get(1) {
+            Some(b'"') => (Some('"'), 2),
+            Some(b'\\') => (Some('\\'), 2),
+            Some(b'/') => (Some('/'), 2),
+            Some(b'b') => (Some('\u{8}'), 2),
+            Some(b'f') => (Some('\u{c}'), 2),
+            Some(b'n') => (Some('\n'), 2),
+            Some(b'r') => (Some('\r'), 2),
+            Some(b't') => (Some('\t'), 2),
+            Some(b'u') => match hex4(&bytes[2..]) {
+                Some(high @ 0xD800..=0xDBFF) => match low_surrogate(&bytes[6..]) {
+                    Some(low) => (char::from_u32(0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00)), 12),
+                    None => (Some(char::REPLACEMENT_CHARACTER), 6),
+                },
+                Some(code) => (Some(char::from_u32(code).unwrap_or(char::REPLACEMENT_CHARACTER)), 6),
+                None => (Some(char::REPLACEMENT_CHARACTER), 2 + hex_prefix(&bytes[2..])),
+            },
+            None => (Some(char::REPLACEMENT_CHARACTER), 1),
+            Some(_) if json5 => json5_unescape(rest),
+            Some(_) => (Some(char::REPLACEMENT_CHARACTER), 1 + rest[1..].chars().next().map_or(0, char::len_utf8)),
+        };
+        if let Some(c) = c {
+            result.push(c);
+        }
+        rest = &rest[len..];
+    }
+    result.push_str(rest);
+    Cow::Owned(result)
+}
+
+/// Decodes a JSON5-only escape at the start of `rest`. A line continuation decodes to nothing.
+fn json5_unescape(rest: &str) -> (Option<char>, usize) {
+    let bytes = rest.as_bytes();
+    let c = rest[1..].chars().next().unwrap_or_default();
+    match c {
+        'v' => (Some('\u{b}'), 2),
+        '0' if !bytes.get(2).is_some_and(u8::is_ascii_digit) => (Some('\0'), 2),
+        '0'..='9' => (Some(char::REPLACEMENT_CHARACTER), 2),
+        'x' => match hex2(&bytes[2..]) {
+            Some(code) => (char::from_u32(code), 4),
+            None => (Some(char::REPLACEMENT_CHARACTER), 2 + hex_prefix(&bytes[2..]).min(2)),
+        },
+        '\r' if bytes.get(2) == Some(&b'\n') => (None, 3),
+        '\n' | '\r' | '\u{2028}' | '\u{2029}' => (None, 1 + c.len_utf8()),
+        c => (Some(c), 1 + c.len_utf8()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn errors(s: &str) -> (Option<u32>, Vec<(u32, u32, ErrorCode)>) {
+        let mut errors = Vec::new();
+        let len = scan(s, false, |r, code, _| errors.push((r.start, r.end, code)));
+        (len, errors)
+    }
+
+    #[test]
+    fn test_valid_escapes() {
+        let s = r#""a\"\\\/\b\f\n\r\té😀" tail"#;
+        assert_eq!(errors(s), (Some(s.len() as u32 - 5), vec![]));
+        assert_eq!(unescape(&s[..s.len() - 5], false), "a\"\\/\u{8}\u{c}\n\r\té😀");
+        assert!(matches!(unescape("\"plain\"", false), Cow::Borrowed("plain")));
+    }
+
+    #[test]
+    fn test_invalid_escapes() {
+        assert_eq!(errors(r#""\x\é""#), (Some(7), vec![(1, 3, ErrorCode::InvalidEscape), (3, 6, ErrorCode::InvalidEscape)]));
+        assert_eq!(errors(r#""\u12G4""#), (Some(8), vec![(1, 5, ErrorCode::InvalidUnicodeEscape)]));
+        assert_eq!(errors(r#""\uD83D x""#), (Some(10), vec![(1, 7, ErrorCode::LoneSurrogate)]));
+        assert_eq!(errors(r#""\uDE00""#), (Some(8), vec![(1, 7, ErrorCode::LoneSurrogate)]));
+        assert_eq!(errors("\"a\tb\""), (Some(5), vec![(2, 3, ErrorCode::ControlCharacter)]));
+        assert_eq!(errors("\"abc\\\n\""), (None, vec![(4, 5, ErrorCode::InvalidEscape)]));
+        assert_eq!(unescape(r#""\x\uD83D!""#, false), "\u{fffd}\u{fffd}!");
+    }
+
+    #[test]
+    fn test_json5_escapes() {
+        let s = "'a\\'\"\\v\\0\\x41\\\n\\q'";
+        let mut errors = Vec::new();
+        assert_eq!(scan(s, true, |r, code, _| errors.push((r.start, r.end, code))), Some(s.len() as u32));
+        assert_eq!(errors, vec![]);
+        assert_eq!(unescape(s, true), "a'\"\u{b}\0Aq");
+        assert_eq!(scan("'\\1\\x4'", true, |r, code, _| errors.push((r.start, r.end, code))), Some(7));
+        assert_eq!(errors, vec![(1, 3, ErrorCode::InvalidEscape), (3, 6, ErrorCode::InvalidEscape)]);
+        assert_eq!(scan("'a\tb'", true, |_, _, _| panic!()), Some(5));
+    }
+}
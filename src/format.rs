@@ -0,0 +1,867 @@
+use std::ops::Range;
+use crate::edit::{self, TextEdit};
+use crate::range::TextRange;
+use crate::source::Source;
+use crate::tokens::{Array, Key, Node, Object, Pair, Trivia, Value};
+use crate::trivia;
+use crate::width::Width;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Indent {
+    Spaces(u8),
+    Tab,
+}
+
+impl Indent {
+    fn unit(self) -> String {
+        match self {
+            Indent::Spaces(n) => " ".repeat(n as usize),
+            Indent::Tab => "\t".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrailingCommas {
+    /// Remove every trailing comma.
+    Never,
+    /// Put a trailing comma after the last member of every container that spans several lines.
+    Multiline,
+    /// Keep the trailing commas of the input on containers that span several lines.
+    Preserve,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FormatOptions {
+    pub indent: Indent,
+    /// Objects and arrays that fit into this many characters, counting the indentation, are put on
+    /// a single line, unless they contain a comment.
+    pub line_width: usize,
+    pub trailing_commas: TrailingCommas,
+    pub final_newline: bool,
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        Self {
+            indent: Indent::Spaces(2),
+            line_width: 80,
+            trailing_commas: TrailingCommas::Never,
+            final_newline: true,
+        }
+    }
+}
+
+/// Pretty-prints a document. Comments are kept: a comment on its own line stays in front of the
+/// pair or item that follows it, and a comment at the end of a line stays at the end of that
+/// member's line. Malformed keys and values are copied as they are, but a missing comma between
+/// members, and a missing colon after a well-formed key, is added.
+pub fn format(source: &Source, options: FormatOptions) -> String {
+    let mut f = Formatter::new(&source.content, options);
+    let root = source.tree();
+    let leading = f.comments(0, root.value.0);
+    for comment in leading.same_line.iter().chain(leading.own_line.iter().map(|(_, c)| c)) {
+        f.out.push_str(comment);
+        f.out.push('\n');
+    }
+    if !f.out.is_empty() && leading.blank_after {
+        f.out.push('\n');
+    }
+    f.value(&root.value.1, root.value.0, 0, &mut Vec::new());
+
+    let offset = root.value.width();
+    let trailing = f.comments(offset, root.trailing.0);
+    f.line_end(&trailing.same_line);
+    for (_, comment) in &trailing.own_line {
+        f.out.push('\n');
+        f.out.push_str(comment);
+    }
+    if let Some(rest) = &root.trailing.1 {
+        f.out.push('\n');
+        f.out.push_str(f.slice(offset + root.trailing.0, rest.width()).trim_end());
+    }
+
+    let len = f.out.trim_end().len();
+    f.out.truncate(len);
+    if options.final_newline && !f.out.is_empty() {
+        f.out.push('\n');
+    }
+    f.out
+}
+
+/// Reformats the smallest run of pairs or items that covers `range`, and leaves the rest of the
+/// document alone. The selected members are indented one level deeper than the line their
+/// container starts on. The edits are as small as possible: where only whitespace changes, each
+/// changed run of whitespace is an edit of its own.
+pub fn format_range(source: &Source, range: TextRange, options: FormatOptions) -> Vec<TextEdit> {
+    let text = &source.content;
+    let root = source.tree().value;
+    let mut f = Formatter::new(text, options);
+    let (start, end) = match select(text, root.1, root.0, range) {
+        Some(Selection::Members { value, offset, members }) => {
+            f.base = edit::line_indent(text, offset).to_string() + &options.indent.unit();
+            let start = match &value.1 {
+                Value::Object(o) => {
+                    let pairs = o.pairs();
+                    pairs[..members.start].iter().map(Width::width).sum::<u32>() + pairs[members.start].key.0
+                }
+                Value::Array(a) => {
+                    let values = a.values();
+                    values[..members.start].iter().map(Width::width).sum::<u32>() + values[members.start].item.0
+                }
+                _ => unreachable!("only containers have members"),
+            };
+            // `{` and `[` are one byte wide.
+            let mut start = offset + 1 + start;
+            // A member that starts its line is reindented as well.
+            if edit::starts_line(text, start) {
+                start = edit::line_start(text, start) as u32;
+                f.out.push_str(&f.base);
+            }
+            f.start_column = columns(&text[edit::line_start(text, start)..start as usize]);
+            let (trivia, end, comma) = match &value.1 {
+                Value::Object(o) => {
+                    let (trivia, end) = f.pairs(o, offset, members.clone(), true);
+                    (trivia, end, o.pairs()[members.end - 1].comma.1.is_some())
+                }
+                Value::Array(a) => {
+                    let (trivia, end) = f.items(a, offset, members.clone(), true);
+                    (trivia, end, a.values()[members.end - 1].comma.1.is_some())
+                }
+                _ => unreachable!("only containers have members"),
+            };
+            let mut end = if comma { end } else { trivia };
+            // A line comment at the end would swallow whatever follows it on the same line, so that
+            // moves to a line of its own.
+            let rest = &text[end as usize..];
+            let next = rest.trim_start_matches([' ', '\t']);
+            if f.line_comment == f.out.len() && !next.is_empty() && !next.starts_with(['\n', '\r']) {
+                end += (rest.len() - next.len()) as u32;
+                f.out.push('\n');
+                if next.starts_with(['}', ']']) {
+                    f.out.push_str(edit::line_indent(text, offset));
+                } else {
+                    f.out.push_str(&f.base);
+                }
+            }
+            (start, end)
+        }
+        Some(Selection::Value { value, offset }) => {
+            f.base = edit::line_indent(text, offset).to_string();
+            f.start_column = columns(&text[edit::line_start(text, offset)..offset as usize]);
+            f.value(&value, offset, 0, &mut Vec::new());
+            (offset, offset + value.width())
+        }
+        None => return Vec::new(),
+    };
+    diff(&text[start as usize..end as usize], &f.out, start)
+}
+
+enum Selection<'t> {
+    /// A value as a whole: the top-level one, or a container that fits on one line.
+    Value { value: Node<Value<'t>>, offset: u32 },
+    /// Some members of the container `value` at `offset`.
+    Members { value: Node<Value<'t>>, offset: u32, members: Range<usize> },
+}
+
+/// Finds what [`format_range`] reformats: the members of the innermost container that strictly
+/// contains `range` that overlap it, or `None` if `range` only covers the trivia between them. A
+/// container that is on a single line is selected as a whole, so its members keep one layout.
+fn select<'t>(text: &str, value: Node<Value<'t>>, offset: u32, range: TextRange) -> Option<Selection<'t>> {
+    let inside = |start: u32, width: u32| start < range.start && range.end < start + width;
+    if range.end < offset || offset + value.width() < range.start {
+        return None;
+    }
+    if !inside(offset, value.width()) || !matches!(value.1, Value::Object(_) | Value::Array(_)) {
+        return Some(Selection::Value { value, offset });
+    }
+    let (mut value, mut offset) = (value, offset);
+    loop {
+        // The span of every member, from after its leading trivia up to its comma or its value, and
+        // the member's value with its start.
+        let mut spans = Vec::new();
+        match &value.1 {
+            Value::Object(o) => {
+                let mut start = offset + o.left_brace().width();
+                for pair in o.pairs() {
+                    let value_start = start + pair.key.width() + pair.colon.width() + pair.value.0;
+                    let end = if pair.comma.1.is_some() { start + pair.width() } else { value_start + pair.value.1.width() };
+                    spans.push((start + pair.key.0, end, pair.value.1, value_start));
+                    start += pair.width();
+                }
+            }
+            Value::Array(a) => {
+                let mut start = offset + a.left_bracket().width();
+                for item in a.values() {
+                    let value_start = start + item.item.0;
+                    let end = if item.comma.1.is_some() { start + item.width() } else { value_start + item.item.1.width() };
+                    spans.push((value_start, end, item.item.1, value_start));
+                    start += item.width();
+                }
+            }
+            _ => unreachable!("only containers are selected"),
+        }
+        let overlaps = |&(start, end, _, _): &(u32, u32, _, _)| {
+            if range.is_empty() {
+                start <= range.start && range.start <= end
+            } else {
+                start < range.end && range.start < end
+            }
+        };
+        let first = spans.iter().position(overlaps)?;
+        let last = spans.iter().rposition(overlaps)?;
+        let (_, _, child, child_start) = spans[first];
+        if first == last && matches!(child.1, Value::Object(_) | Value::Array(_)) && inside(child_start, child.width()) {
+            (value, offset) = (child, child_start);
+            continue;
+        }
+        if !text[offset as usize..(offset + value.width()) as usize].contains('\n') {
+            return Some(Selection::Value { value, offset });
+        }
+        return Some(Selection::Members { value, offset, members: first..last + 1 });
+    }
+}
+
+/// The edits that turn `old`, which starts at `offset`, into `new`. Where the two only differ in
+/// whitespace, every changed run of whitespace becomes an edit; otherwise the part between their
+/// common prefix and suffix is replaced as a whole.
+fn diff(old: &str, new: &str, offset: u32) -> Vec<TextEdit> {
+    let (o, n) = (old.as_bytes(), new.as_bytes());
+    let whitespace = |bytes: &[u8]| bytes.iter().take_while(|b| b.is_ascii_whitespace()).count();
+    let mut edits = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    loop {
+        let (a, b) = (whitespace(&o[i..]), whitespace(&n[j..]));
+        if old[i..i + a] != new[j..j + b] {
+            edits.push(TextEdit::replace(TextRange::at(offset + i as u32, a as u32), &new[j..j + b]));
+        }
+        i += a;
+        j += b;
+        let same = o[i..].iter().zip(&n[j..]).take_while(|(x, y)| x == y && !x.is_ascii_whitespace()).count();
+        i += same;
+        j += same;
+        match (o.get(i), n.get(j)) {
+            (None, None) => return edits,
+            (Some(x), _) if x.is_ascii_whitespace() => {}
+            (_, Some(y)) if y.is_ascii_whitespace() => {}
+            _ => break,
+        }
+    }
+    let prefix = old.char_indices().zip(new.chars()).find(|((_, x), y)| x != y).map_or(old.len().min(new.len()), |((i, _), _)| i);
+    let suffix = old[prefix..].chars().rev().zip(new[prefix..].chars().rev()).take_while(|(x, y)| x == y).map(|(x, _)| x.len_utf8()).sum::<usize>();
+    let range = TextRange::new(offset + prefix as u32, offset + (old.len() - suffix) as u32);
+    vec![TextEdit::replace(range, &new[prefix..new.len() - suffix])]
+}
+
+fn columns(line: &str) -> usize {
+    line.chars().map(|c| if c == '\t' { 4 } else { 1 }).sum()
+}
+
+/// The comments in a run of trivia, without their trailing newlines.
+struct Comments<'a> {
+    /// Comments before the first line break, which belong to whatever precedes the trivia.
+    same_line: Vec<&'a str>,
+    /// Comments on lines of their own, and whether a blank line precedes each of them.
+    own_line: Vec<(bool, &'a str)>,
+    /// Whether a blank line follows the last comment, or the start if there is none.
+    blank_after: bool,
+}
+
+impl Comments<'_> {
+    fn is_empty(&self) -> bool {
+        self.same_line.is_empty() && self.own_line.is_empty()
+    }
+}
+
+struct Formatter<'a> {
+    text: &'a str,
+    options: FormatOptions,
+    out: String,
+    depth: usize,
+    /// Indentation in front of every line after the first, before the indentation for `depth`.
+    base: String,
+    /// The column the first line starts at.
+    start_column: usize,
+    /// The length of `out` just after the last comment nothing may follow on its line: a line
+    /// comment at the end of a line, or a comment that went on a line of its own after one.
+    line_comment: usize,
+}
+
+impl<'a> Formatter<'a> {
+    fn new(text: &'a str, options: FormatOptions) -> Self {
+        Formatter { text, options, out: String::new(), depth: 0, base: String::new(), start_column: 0, line_comment: 0 }
+    }
+
+    fn slice(&self, offset: u32, len: u32) -> &'a str {
+        &self.text[offset as usize..(offset + len) as usize]
+    }
+
+    fn comments(&self, offset: u32, len: u32) -> Comments<'a> {
+        let mut comments = Comments { same_line: Vec::new(), own_line: Vec::new(), blank_after: false };
+        let mut newlines = 0;
+        let mut seen_newline = false;
+        for (kind, token) in trivia::split(self.slice(offset, len)) {
+            match kind {
+                Trivia::Whitespace => {
+                    let n = token.matches('\n').count();
+                    newlines += n;
+                    seen_newline |= n > 0;
+                    continue;
+                }
+                _ if !seen_newline => comments.same_line.push(token.trim_end()),
+                _ => comments.own_line.push((newlines >= 2, token.trim_end())),
+            }
+            // A line comment ends with its newline.
+            newlines = if kind == Trivia::LineComment { 1 } else { 0 };
+            seen_newline |= kind == Trivia::LineComment;
+        }
+        comments.blank_after = newlines >= 2;
+        comments
+    }
+
+    fn newline(&mut self) {
+        let len = self.out.trim_end_matches([' ', '\t']).len();
+        self.out.truncate(len);
+        self.out.push('\n');
+        self.out.push_str(&self.base);
+        for _ in 0..self.depth {
+            self.out.push_str(&self.options.indent.unit());
+        }
+    }
+
+    fn column(&self) -> usize {
+        let (start, line) = match self.out.rfind('\n') {
+            Some(i) => (0, &self.out[i + 1..]),
+            None => (self.start_column, &self.out[..]),
+        };
+        start + columns(line)
+    }
+
+    /// Appends comments to the end of the current line. Nothing can follow a line comment, so every
+    /// comment after one goes on a line of its own, as it would be after formatting again.
+    fn line_end(&mut self, comments: &[&str]) {
+        for comment in comments {
+            let own_line = self.line_comment == self.out.len();
+            if own_line {
+                self.newline();
+            } else {
+                self.out.push(' ');
+            }
+            self.out.push_str(comment);
+            if own_line || comment.starts_with("//") {
+                self.line_comment = self.out.len();
+            }
+        }
+    }
+
+    /// Writes the comments inside a member, e.g. between the key and the colon. Block comments stay
+    /// where they are; line comments cannot, so they are moved to the end of the member's line, and
+    /// the comments after them too to keep them in order.
+    fn inline(&mut self, offset: u32, len: u32, before: bool, deferred: &mut Vec<&'a str>) {
+        let comments = self.comments(offset, len);
+        for comment in comments.same_line.into_iter().chain(comments.own_line.into_iter().map(|(_, c)| c)) {
+            if comment.starts_with("//") || !deferred.is_empty() {
+                deferred.push(comment);
+            } else if before {
+                self.out.push_str(comment);
+                self.out.push(' ');
+            } else {
+                self.out.push(' ');
+                self.out.push_str(comment);
+            }
+        }
+    }
+
+    /// `reserve` is the room to leave on the line after the value, e.g. for a comma. The `deferred`
+    /// line comments in front of a container that spans lines go after its opening bracket, ahead of
+    /// the comments inside it.
+    fn value(&mut self, node: &Node<Value>, offset: u32, reserve: usize, deferred: &mut Vec<&'a str>) {
+        if matches!(node.1, Value::Object(_) | Value::Array(_)) {
+            if let Some(flat) = self.flat(node, offset) {
+                if self.column() + flat.len() + reserve <= self.options.line_width {
+                    self.out.push_str(&flat);
+                    return;
+                }
+            }
+        }
+        match &node.1 {
+            Value::Object(o) => self.object(o, offset, deferred),
+            Value::Array(a) => self.array(a, offset, deferred),
+            _ => self.out.push_str(self.slice(offset, node.width()).trim_end()),
+        }
+    }
+
+    /// The value on a single line, or `None` if it contains a comment or a line break.
+    fn flat(&self, node: &Node<Value>, offset: u32) -> Option<String> {
+        let no_comments = |offset, len| self.comments(offset, len).is_empty();
+        match &node.1 {
+            Value::Object(o) => {
+                let mut offset = offset + o.left_brace().width();
+                let mut members = Vec::new();
+                for pair in &o.pairs() {
+                    let key_start = offset + pair.key.0;
+                    let value_start = key_start + pair.key.1.width() + pair.colon.width();
+                    let value_end = value_start + pair.value.width();
+                    if !(no_comments(offset, pair.key.0) && no_comments(key_start + pair.key.1.width(), pair.colon.0)
+                        && no_comments(value_start, pair.value.0) && no_comments(value_end, pair.comma.0))
+                    {
+                        return None;
+                    }
+                    let key = self.slice(key_start, pair.key.1.width()).trim_end();
+                    let value = self.flat(&pair.value.1, value_start + pair.value.0)?;
+                    let colon = if has_colon(pair) { ": " } else { " " };
+                    members.push(format!("{}{}{}", key, colon, value).trim().to_string());
+                    offset += pair.width();
+                }
+                if o.right_brace().1.is_none() || !no_comments(offset, o.right_brace().0) {
+                    return None;
+                }
+                Some(if members.is_empty() { "{}".to_string() } else { format!("{{ {} }}", members.join(", ")) })
+            }
+            Value::Array(a) => {
+                let mut offset = offset + a.left_bracket().width();
+                let mut members = Vec::new();
+                for item in &a.values() {
+                    let value_end = offset + item.item.width();
+                    if !(no_comments(offset, item.item.0) && no_comments(value_end, item.comma.0)) {
+                        return None;
+                    }
+                    members.push(self.flat(&item.item.1, offset + item.item.0)?);
+                    offset += item.width();
+                }
+                if a.right_bracket().1.is_none() || !no_comments(offset, a.right_bracket().0) {
+                    return None;
+                }
+                Some(format!("[{}]", members.join(", ")))
+            }
+            _ => {
+                let text = self.slice(offset, node.width()).trim_end();
+                (!text.contains('\n')).then(|| text.to_string())
+            }
+        }
+    }
+
+    fn object(&mut self, object: &Object, offset: u32, deferred: &mut Vec<&'a str>) {
+        self.out.push('{');
+        self.depth += 1;
+        self.line_end(deferred);
+        deferred.clear();
+        let count = object.pairs().len();
+        let (trivia, end) = self.pairs(object, offset, 0..count, false);
+        let close = object.right_brace();
+        self.close(trivia, end + close.0 - trivia, count == 0, close.1.map(|_| '}'));
+    }
+
+    fn array(&mut self, array: &Array, offset: u32, deferred: &mut Vec<&'a str>) {
+        self.out.push('[');
+        self.depth += 1;
+        self.line_end(deferred);
+        deferred.clear();
+        let count = array.values().len();
+        let (trivia, end) = self.items(array, offset, 0..count, false);
+        let close = array.right_bracket();
+        self.close(trivia, end + close.0 - trivia, count == 0, close.1.map(|_| ']'));
+    }
+
+    /// Writes the pairs in `members` of the object at `offset`, each on a line of its own. With
+    /// `continued`, the first one goes on the current line and its leading trivia is left out.
+    /// Returns where the trivia after the last one starts, and where the last one ends.
+    fn pairs(&mut self, object: &Object, offset: u32, members: Range<usize>, continued: bool) -> (u32, u32) {
+        let pairs = object.pairs();
+        let mut offset = offset + object.left_brace().width() + pairs[..members.start].iter().map(Width::width).sum::<u32>();
+        let mut trivia = offset;
+        let count = pairs.len();
+        for (i, pair) in pairs.iter().enumerate().take(members.end).skip(members.start) {
+            let key_start = offset + pair.key.0;
+            if !(continued && i == members.start) {
+                self.member_start(trivia, key_start - trivia, i == 0);
+            }
+            let mut deferred = Vec::new();
+            let key_end = key_start + pair.key.1.width();
+            self.out.push_str(self.slice(key_start, pair.key.1.width()).trim_end());
+            self.inline(key_end, pair.colon.0, false, &mut deferred);
+            self.out.push_str(if has_colon(pair) { ": " } else { " " });
+            let value_start = key_end + pair.colon.width();
+            let value_end = value_start + pair.value.width();
+            self.inline(value_start, pair.value.0, true, &mut deferred);
+            let comma = self.has_comma(i + 1 == count, pair.comma.1.is_some()) && !is_unclosed(&pair.value.1 .1);
+            self.value(&pair.value.1, value_start + pair.value.0, comma as usize, &mut deferred);
+            trivia = self.member_end(value_end, pair.comma.0, pair.comma.1.is_some(), comma, &mut deferred);
+            offset += pair.width();
+        }
+        (trivia, offset)
+    }
+
+    /// Like [`Formatter::pairs`], for arrays.
+    fn items(&mut self, array: &Array, offset: u32, members: Range<usize>, continued: bool) -> (u32, u32) {
+        let values = array.values();
+        let mut offset = offset + array.left_bracket().width() + values[..members.start].iter().map(Width::width).sum::<u32>();
+        let mut trivia = offset;
+        let count = values.len();
+        for (i, item) in values.iter().enumerate().take(members.end).skip(members.start) {
+            let value_start = offset + item.item.0;
+            if !(continued && i == members.start) {
+                self.member_start(trivia, value_start - trivia, i == 0);
+            }
+            let mut deferred = Vec::new();
+            let comma = self.has_comma(i + 1 == count, item.comma.1.is_some()) && !is_unclosed(&item.item.1 .1);
+            self.value(&item.item.1, value_start, comma as usize, &mut deferred);
+            trivia = self.member_end(offset + item.item.width(), item.comma.0, item.comma.1.is_some(), comma, &mut deferred);
+            offset += item.width();
+        }
+        (trivia, offset)
+    }
+
+    /// Finishes a member's line after its value, and returns where the trivia in front of the next
+    /// member starts. Without a comma, the trivia the comma would have followed is really the trivia
+    /// in front of the next member or the closing bracket.
+    fn member_end(&mut self, offset: u32, len: u32, present: bool, comma: bool, deferred: &mut Vec<&'a str>) -> u32 {
+        if present {
+            self.inline(offset, len, false, deferred);
+        }
+        if comma {
+            self.out.push(',');
+        }
+        self.line_end(deferred);
+        if present {
+            offset + len + 1
+        } else {
+            offset
+        }
+    }
+
+    /// Handles the trivia in front of a member and starts its line. Comments before the first line
+    /// break end the previous line, the others go on their own lines in front of the member.
+    fn member_start(&mut self, offset: u32, len: u32, first: bool) {
+        let comments = self.comments(offset, len);
+        self.line_end(&comments.same_line);
+        for (blank, comment) in &comments.own_line {
+            if *blank && !first {
+                self.newline();
+            }
+            self.newline();
+            self.out.push_str(comment);
+        }
+        if comments.blank_after && !first {
+            self.newline();
+        }
+        self.newline();
+    }
+
+    fn has_comma(&self, last: bool, present: bool) -> bool {
+        !last
+            || match self.options.trailing_commas {
+                TrailingCommas::Never => false,
+                TrailingCommas::Multiline => true,
+                TrailingCommas::Preserve => present,
+            }
+    }
+
+    /// Writes the comments in front of the closing bracket and the bracket itself, if there is one.
+    fn close(&mut self, offset: u32, len: u32, empty: bool, bracket: Option<char>) {
+        let comments = self.comments(offset, len);
+        self.line_end(&comments.same_line);
+        for (blank, comment) in &comments.own_line {
+            if *blank && !empty {
+                self.newline();
+            }
+            self.newline();
+            self.out.push_str(comment);
+        }
+        self.depth -= 1;
+        if let Some(bracket) = bracket {
+            if !(empty && comments.is_empty()) || self.line_comment == self.out.len() {
+                self.newline();
+            }
+            self.out.push(bracket);
+        }
+    }
+}
+
+/// A missing colon is added, unless the key is malformed too and the pair is mostly guesswork.
+fn has_colon(pair: &Pair) -> bool {
+    pair.colon.1.is_some() || !matches!(pair.key.1 .1, Key::Invalid(_))
+}
+
+/// A comma after an unclosed container would become part of it.
+fn is_unclosed(value: &Value) -> bool {
+    match value {
+        Value::Object(o) => o.right_brace().1.is_none(),
+        Value::Array(a) => a.right_bracket().1.is_none(),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+    use crate::test_support::Rng;
+
+    fn fmt(text: &str, options: FormatOptions) -> String {
+        let formatted = format(&parse(text.to_string()), options);
+        assert_eq!(format(&parse(formatted.clone()), options), formatted, "not idempotent");
+        formatted
+    }
+
+    #[test]
+    fn test_indent_and_width() {
+        let text = r#"{"name":"lst","tags":["json","cst"],"deps":{"ropey":"1.6.1"},"empty":[],"nested":{"a":[1,2,{"b":null}]}}"#;
+        assert_eq!(fmt(text, FormatOptions::default()), concat!(
+            "{\n",
+            "  \"name\": \"lst\",\n",
+            "  \"tags\": [\"json\", \"cst\"],\n",
+            "  \"deps\": { \"ropey\": \"1.6.1\" },\n",
+            "  \"empty\": [],\n",
+            "  \"nested\": { \"a\": [1, 2, { \"b\": null }] }\n",
+            "}\n",
+        ));
+        let narrow = FormatOptions { indent: Indent::Tab, line_width: 30, final_newline: false, ..FormatOptions::default() };
+        assert_eq!(fmt(r#"{"tags":["json","cst"],"nested":{"a":[1,2,{"b":null}]}}"#, narrow), concat!(
+            "{\n",
+            "\t\"tags\": [\"json\", \"cst\"],\n",
+            "\t\"nested\": {\n",
+            "\t\t\"a\": [\n",
+            "\t\t\t1,\n",
+            "\t\t\t2,\n",
+            "\t\t\t{ \"b\": null }\n",
+            "\t\t]\n",
+            "\t}\n",
+            "}",
+        ));
+        assert_eq!(fmt("[ ]", FormatOptions::default()), "[]\n");
+    }
+
+    #[test]
+    fn test_trailing_commas() {
+        let text = "{\"a\": [1, 2,], \"bb\": [3,\n4]}";
+        let options = |trailing_commas| FormatOptions { line_width: 15, trailing_commas, ..FormatOptions::default() };
+        assert_eq!(fmt(text, options(TrailingCommas::Never)), "{\n  \"a\": [1, 2],\n  \"bb\": [3, 4]\n}\n");
+        assert_eq!(fmt(text, options(TrailingCommas::Multiline)), "{\n  \"a\": [1, 2],\n  \"bb\": [3, 4],\n}\n");
+        let text = "[{\"a\": 1,}, {\"b\": 2}]";
+        let options = FormatOptions { line_width: 10, trailing_commas: TrailingCommas::Preserve, ..FormatOptions::default() };
+        assert_eq!(fmt(text, options), "[\n  {\n    \"a\": 1,\n  },\n  {\n    \"b\": 2\n  }\n]\n");
+    }
+
+    #[test]
+    fn test_comments() {
+        let text = "// header\n\n{ // opener\n  // about a\n  \"a\": 1, // after a\n\n  /* about b */ \"b\" /* key */ : [2, 3] // after b\n  // dangling\n} // end\n";
+        assert_eq!(fmt(text, FormatOptions::default()), concat!(
+            "// header\n",
+            "\n",
+            "{ // opener\n",
+            "  // about a\n",
+            "  \"a\": 1, // after a\n",
+            "\n",
+            "  /* about b */\n",
+            "  \"b\" /* key */: [2, 3] // after b\n",
+            "  // dangling\n",
+            "} // end\n",
+        ));
+        // A comment keeps its container from collapsing, and a line comment inside a pair moves to
+        // the end of the line.
+        assert_eq!(fmt("[1, /* two */ 2]", FormatOptions::default()), "[\n  1, /* two */\n  2\n]\n");
+        assert_eq!(fmt("{\"a\": // c\n 1}", FormatOptions::default()), "{\n  \"a\": 1 // c\n}\n");
+        assert_eq!(
+            fmt("{\"a\": 1 // x\n// y\n, \"b\": 2}", FormatOptions::default()),
+            "{\n  \"a\": 1, // x\n  // y\n  \"b\": 2\n}\n"
+        );
+        // Every comment after a line comment goes on a line of its own, in source order.
+        assert_eq!(fmt("[1 // x\n, /* p */ /* q */ 2]", FormatOptions::default()), "[\n  1, // x\n  /* p */\n  /* q */\n  2\n]\n");
+        assert_eq!(fmt("[{}// a\n/* b */, 2]", FormatOptions::default()), "[\n  {}, // a\n  /* b */\n  2\n]\n");
+        assert_eq!(fmt("{\"a\": // x\n[ // y\n]}", FormatOptions::default()), "{\n  \"a\": [ // x\n    // y\n  ]\n}\n");
+    }
+
+    /// Malformed keys and values are kept, and a missing colon or comma is added.
+    #[test]
+    fn test_malformed_parts_are_kept() {
+        assert_eq!(fmt("{\"a\" 1 \"b\": tru}", FormatOptions::default()), "{ \"a\": 1, \"b\": tru }\n");
+        assert_eq!(fmt("[1] x ", FormatOptions::default()), "[1]\nx\n");
+    }
+
+    /// A missing closing bracket is not added, so the formatted document has the same errors.
+    #[test]
+    fn test_unclosed_containers() {
+        assert_eq!(fmt("{\"a\": [1, 2}", FormatOptions::default()), "{\n  \"a\": [\n    1,\n    2\n}\n");
+        let options = FormatOptions { trailing_commas: TrailingCommas::Multiline, ..FormatOptions::default() };
+        assert_eq!(fmt("{\"a\": [1 // c\n}", options), "{\n  \"a\": [\n    1, // c\n}\n");
+    }
+
+    fn format_range_at(text: &str, range: TextRange) -> (String, Vec<TextEdit>) {
+        let edits = format_range(&parse(text.to_string()), range, FormatOptions::default());
+        (crate::apply_edits(text, &edits), edits)
+    }
+
+    #[test]
+    fn test_format_range() {
+        let text = "{\n    \"a\":1,\n    \"b\" :   [1,2,\n3],\n    \"c\":{\"x\":  1},\n    \"d\":true\n}";
+        let at = |needle: &str| text.find(needle).unwrap() as u32;
+        // Only the pair that contains the selection changes, indented one level below its container.
+        let (formatted, edits) = format_range_at(text, TextRange::at(at("\"b\""), 3));
+        assert_eq!(formatted, "{\n    \"a\":1,\n  \"b\": [1, 2, 3],\n    \"c\":{\"x\":  1},\n    \"d\":true\n}");
+        assert!(edits.len() > 1 && edits.iter().all(|e| e.range.start >= at("    \"b\"") && e.range.end <= at("\n    \"c\"")));
+
+        // A cursor inside a nested container selects it, and one on a single line is reformatted
+        // as a whole.
+        let (formatted, _) = format_range_at(text, TextRange::empty(at("1}")));
+        assert_eq!(formatted, text.replace("{\"x\":  1}", "{ \"x\": 1 }"));
+        let (formatted, _) = format_range_at("[1,2,  3]", TextRange::at(7, 1));
+        assert_eq!(formatted, "[1, 2, 3]");
+        let (formatted, _) = format_range_at("[\n  1,2,\n  [3,4]  ]", TextRange::at(4, 3));
+        assert_eq!(formatted, "[\n  1,\n  2,\n  [3,4]  ]");
+
+        // A selection across several members reformats all of them, and the lines between them.
+        let (formatted, _) = format_range_at(text, TextRange::new(at("\"a\""), at("\"c\"") + 1));
+        assert_eq!(formatted, "{\n  \"a\": 1,\n  \"b\": [1, 2, 3],\n  \"c\": { \"x\": 1 },\n    \"d\":true\n}");
+
+        // Nothing to do between members, and a selection covering the brackets formats everything.
+        assert!(format_range_at(text, TextRange::empty(at("\n    \"d\"") + 1)).1.is_empty());
+        let (formatted, _) = format_range_at(text, TextRange::new(0, text.len() as u32));
+        assert_eq!(formatted, format(&parse(text.to_string()), FormatOptions::default()).trim_end());
+    }
+
+    #[test]
+    fn test_format_range_comments() {
+        // A trailing line comment must not swallow what follows it on the same line.
+        let (formatted, _) = format_range_at("{\n  \"a\": // c\n  1, \"b\": 2\n}", TextRange::empty(13));
+        assert_eq!(formatted, "{\n  \"a\": 1, // c\n  \"b\": 2\n}");
+        let (formatted, _) = format_range_at("{\n  \"a\": // c\n  1 }", TextRange::empty(13));
+        assert_eq!(formatted, "{\n  \"a\": 1 // c\n}");
+    }
+
+    /// A pseudo-random document full of comments, and its comments in source order. Each comment
+    /// has a distinct name, so none contains another.
+    fn random_document(rng: &mut Rng) -> (String, Vec<String>) {
+        let mut text = String::new();
+        let mut comments = Vec::new();
+        let mut trivia = |text: &mut String, rng: &mut Rng| {
+            for _ in 0..2 {
+                let n = comments.len();
+                match rng.next(12) % 6 {
+                    0 => text.push(' '),
+                    1 => text.push_str("\n  "),
+                    2 => comments.push(format!("// c{}.", n)),
+                    3 => comments.push(format!("/* c{}. */", n)),
+                    _ => continue,
+                }
+                if comments.len() > n {
+                    text.push(' ');
+                    text.push_str(&comments[n]);
+                    text.push(if comments[n].starts_with("//") { '\n' } else { ' ' });
+                }
+            }
+        };
+        let mut open = vec![];
+        let mut members = vec![0];
+        loop {
+            trivia(&mut text, rng);
+            let depth = open.len();
+            let count = *members.last().unwrap();
+            if depth > 0 && (count >= 3 || rng.next(3) == 0) {
+                text.push(open.pop().unwrap());
+                members.pop();
+                if open.is_empty() {
+                    break;
+                }
+                continue;
+            }
+            if count > 0 {
+                text.push(',');
+                trivia(&mut text, rng);
+            }
+            *members.last_mut().unwrap() += 1;
+            if open.last() == Some(&'}') {
+                text.push_str(&format!("\"k{}\"", count));
+                trivia(&mut text, rng);
+                text.push(':');
+                trivia(&mut text, rng);
+            }
+            match rng.next(if depth < 3 { 4 } else { 2 }) {
+                0 => text.push('1'),
+                1 => text.push_str("\"s\""),
+                2 => {
+                    text.push('[');
+                    open.push(']');
+                    members.push(0);
+                }
+                _ => {
+                    text.push('{');
+                    open.push('}');
+                    members.push(0);
+                }
+            }
+            if open.is_empty() {
+                break;
+            }
+        }
+        (text, comments)
+    }
+
+    /// Formats pseudo-random ranges of pseudo-random documents full of comments, and checks that
+    /// the result still parses to the same value and keeps every comment.
+    #[test]
+    fn test_format_range_random() {
+        let mut rng = Rng::new(0x2545_f491_4f6c_dd1d);
+        for _ in 0..300 {
+            let (text, comments) = random_document(&mut rng);
+            let expected = crate::minify(&parse(text.clone())).unwrap();
+            for _ in 0..5 {
+                let start = rng.next(text.len() + 1);
+                let end = (start + rng.next(8)).min(text.len());
+                let (formatted, _) = format_range_at(&text, TextRange::new(start as u32, end as u32));
+                let reparsed = parse(formatted.clone());
+                assert!(!reparsed.has_error(), "{:?} => {:?}", text, formatted);
+                assert_eq!(crate::minify(&reparsed).unwrap(), expected, "{:?} => {:?}", text, formatted);
+                for comment in &comments {
+                    assert_eq!(formatted.matches(comment.as_str()).count(), 1, "{:?} => {:?}", text, formatted);
+                }
+            }
+        }
+    }
+
+    /// Formats pseudo-random documents full of comments, and checks that formatting again changes
+    /// nothing and that the comments stay in source order.
+    #[test]
+    fn test_format_random() {
+        let mut rng = Rng::new(0x9e37_79b9_7f4a_7c15);
+        for _ in 0..300 {
+            let (text, comments) = random_document(&mut rng);
+            let trailing_commas = [TrailingCommas::Never, TrailingCommas::Multiline, TrailingCommas::Preserve][rng.next(3)];
+            let options = FormatOptions { trailing_commas, line_width: [20, 80][rng.next(2)], ..FormatOptions::default() };
+            let formatted = format(&parse(text.clone()), options);
+            assert_eq!(format(&parse(formatted.clone()), options), formatted, "{:?}", text);
+            let positions: Vec<_> = comments.iter().map(|c| formatted.find(c.as_str())).collect();
+            assert!(positions.windows(2).all(|w| w[0] < w[1]), "{:?} => {:?}", text, formatted);
+        }
+    }
+
+    #[test]
+    fn test_minimal_edits() {
+        assert_eq!(diff("[1,2]", "[1, 2]", 10), vec![TextEdit::insert(13, " ")]);
+        assert_eq!(diff("{\"a\":1 }", "{ \"a\": 1 }", 0), vec![TextEdit::insert(1, " "), TextEdit::insert(5, " ")]);
+        assert_eq!(diff("[1,]", "[1]", 0), vec![TextEdit::delete(TextRange::new(2, 3))]);
+        assert_eq!(diff("\"é\"x", "\"é\"y", 0), vec![TextEdit::replace(TextRange::new(4, 5), "y")]);
+    }
+
+    /// Every prefix of a document is broken in some way; formatting must still keep every comment.
+    #[test]
+    fn test_prefixes() {
+        let text = "// a\n{ /* b */ \"k\" /* c */: [1, // d\n 2 /* e */], \"é\": {\"x\": tru // f\n}} // g\n";
+        for end in (0..=text.len()).filter(|&i| text.is_char_boundary(i)) {
+            let prefix = &text[..end];
+            let formatted = format(&parse(prefix.to_string()), FormatOptions::default());
+            for comment in ["// a", "/* b */", "/* c */", "// d", "/* e */", "// f", "// g"] {
+                assert_eq!(prefix.contains(comment), formatted.contains(comment), "{:?} => {:?}", prefix, formatted);
+            }
+        }
+    }
+
+    #[test]
+    fn test_multi() {
+        let source = parse(include_str!("../tests/data/multi.json").to_string());
+        let formatted = format(&source, FormatOptions { line_width: 30, ..FormatOptions::default() });
+        let reparsed = parse(formatted.clone());
+        assert!(!reparsed.has_error());
+        assert_eq!(format(&reparsed, FormatOptions { line_width: 30, ..FormatOptions::default() }), formatted);
+    }
+}
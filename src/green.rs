@@ -0,0 +1,488 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::{BuildHasherDefault, Hash, Hasher};
+use std::sync::Arc;
+use crate::parse::ErrorCode;
+use crate::syntax::SyntaxKind;
+use crate::width::Width;
+use crate::{Linearize, LinearizeBuffer};
+
+/// A leaf of the green tree. Like nodes, tokens store no text, only their kind and width, so a
+/// token is a small value that is copied rather than allocated. `error` is set for a malformed
+/// token: an [`SyntaxKind::Error`], or a string with a bad escape or control character.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct GreenToken {
+    kind: SyntaxKind,
+    error: Option<ErrorCode>,
+    width: u32,
+}
+
+impl GreenToken {
+    pub fn new(kind: SyntaxKind, width: u32) -> Self {
+        Self { kind, error: None, width }
+    }
+
+    pub fn error(code: ErrorCode, width: u32) -> Self {
+        Self { kind: SyntaxKind::Error, error: Some(code), width }
+    }
+
+    /// A token of `kind` that is malformed, e.g. a string with an invalid escape.
+    pub fn malformed(kind: SyntaxKind, code: Option<ErrorCode>, width: u32) -> Self {
+        Self { kind, error: code, width }
+    }
+
+    pub fn kind(&self) -> SyntaxKind {
+        self.kind
+    }
+
+    pub fn code(&self) -> Option<ErrorCode> {
+        self.error
+    }
+}
+
+impl Width for GreenToken {
+    fn width(&self) -> u32 {
+        self.width
+    }
+}
+
+impl fmt::Debug for GreenToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.error {
+            Some(code) => write!(f, "{:?}({})@{}", self.kind, code, self.width),
+            None => write!(f, "{:?}@{}", self.kind, self.width),
+        }
+    }
+}
+
+/// An immutable node of the lossless tree: its kind, its width, whether anything in it failed to
+/// parse, and its children in a flat array. Positions are not stored, so identical subtrees are
+/// shared wherever they occur, and cloning a node or a whole tree only bumps a reference count.
+///
+/// Whitespace and comments are tokens of the node they lead into, so that `[ 1, 2 ]` is an
+/// `Array` of `[`, an `Item` of ` ` `1` `,`, an `Item` of ` ` `2` ` `, and `]`.
+#[derive(Clone)]
+pub struct GreenNode(Arc<[GreenElement]>);
+
+#[derive(Clone, PartialEq)]
+pub enum GreenElement {
+    Node(GreenNode),
+    Token(GreenToken),
+}
+
+impl GreenNode {
+    pub fn new(kind: SyntaxKind, mut children: Vec<GreenElement>) -> Self {
+        Self::drain(kind, &mut children, 0)
+    }
+
+    /// Moves the elements of `children` from `first` on into a new node. The node and its children
+    /// are a single allocation: the first element is a header token of the node's kind and width,
+    /// with the code of a problem in it if there is one.
+    fn drain(kind: SyntaxKind, children: &mut Vec<GreenElement>, first: usize) -> Self {
+        let width = children[first..].iter().map(Width::width).sum();
+        let header = GreenToken { kind, error: error(kind, &children[first..]), width };
+        GreenNode(std::iter::once(GreenElement::Token(header)).chain(children.drain(first..)).collect())
+    }
+
+    fn header(&self) -> &GreenToken {
+        match &self.0[0] {
+            GreenElement::Token(header) => header,
+            GreenElement::Node(_) => unreachable!("a node starts with its header"),
+        }
+    }
+
+    pub fn kind(&self) -> SyntaxKind {
+        self.header().kind
+    }
+
+    /// Whether this node or anything below it failed to parse, including a pair without a colon
+    /// and members that are missing the comma separating them from the next one.
+    pub fn has_error(&self) -> bool {
+        self.header().error.is_some()
+    }
+
+    pub fn children(&self) -> &[GreenElement] {
+        &self.0[1..]
+    }
+
+    /// A copy of this node with the child at `index` swapped for `child`. The other children are
+    /// shared with this node.
+    pub fn replace_child(&self, index: usize, child: GreenElement) -> GreenNode {
+        let mut children = self.children().to_vec();
+        children[index] = child;
+        GreenNode::new(self.kind(), children)
+    }
+
+    /// The bytes allocated for this tree, counting every shared node once.
+    pub fn memory(&self) -> usize {
+        let mut seen = std::collections::HashSet::new();
+        let mut stack = vec![self];
+        let mut total = 0;
+        while let Some(node) = stack.pop() {
+            if !seen.insert(node.address()) {
+                continue;
+            }
+            // The reference counts, then the header and the children.
+            total += 2 * std::mem::size_of::<usize>() + std::mem::size_of_val(&*node.0);
+            stack.extend(node.children().iter().filter_map(GreenElement::as_node));
+        }
+        total
+    }
+
+    fn address(&self) -> usize {
+        Arc::as_ptr(&self.0).cast::<u8>() as usize
+    }
+}
+
+/// See [`GreenNode::has_error`]. A member whose comma is missing is caught by its container, since
+/// only the container knows whether another member follows.
+fn error(kind: SyntaxKind, children: &[GreenElement]) -> Option<ErrorCode> {
+    if let Some(code) = children.iter().find_map(GreenElement::code) {
+        return Some(code);
+    }
+    match kind {
+        SyntaxKind::Pair if !children.iter().any(|c| c.kind() == SyntaxKind::Colon) => Some(ErrorCode::MissingColon),
+        SyntaxKind::Object if children.last().map(GreenElement::kind) != Some(SyntaxKind::RightBrace) => Some(ErrorCode::UnclosedObject),
+        SyntaxKind::Array if children.last().map(GreenElement::kind) != Some(SyntaxKind::RightBracket) => Some(ErrorCode::UnclosedArray),
+        SyntaxKind::Object | SyntaxKind::Array => {
+            let mut members = children.iter().filter_map(GreenElement::as_node);
+            let mut member = members.next();
+            while let (Some(current), next @ Some(_)) = (member, members.next()) {
+                if current.children().last().map(GreenElement::kind) != Some(SyntaxKind::Comma) {
+                    return Some(ErrorCode::MissingComma);
+                }
+                member = next;
+            }
+            None
+        }
+        _ => None,
+    }
+}
+
+impl Width for GreenNode {
+    fn width(&self) -> u32 {
+        self.header().width
+    }
+}
+
+/// Nodes are equal if they have the same structure. Identical subtrees are usually the same
+/// allocation, which makes comparing them cheap. Like everything else that walks the tree, this
+/// keeps its own stack, so that the depth of the tree is not limited by the call stack.
+impl PartialEq for GreenNode {
+    fn eq(&self, other: &Self) -> bool {
+        let mut stack = vec![(self, other)];
+        while let Some((a, b)) = stack.pop() {
+            if Arc::ptr_eq(&a.0, &b.0) {
+                continue;
+            }
+            if a.0.len() != b.0.len() {
+                return false;
+            }
+            for pair in a.0.iter().zip(b.0.iter()) {
+                match pair {
+                    (GreenElement::Node(a), GreenElement::Node(b)) => stack.push((a, b)),
+                    (GreenElement::Token(a), GreenElement::Token(b)) if a == b => {}
+                    _ => return false,
+                }
+            }
+        }
+        true
+    }
+}
+
+/// Dropping the last reference to a node drops its children, which would recurse once per level.
+/// Instead, the children that are not shared are moved onto a stack, and each is emptied the same
+/// way before it is dropped.
+impl Drop for GreenNode {
+    fn drop(&mut self) {
+        let mut stack = Vec::new();
+        self.take_children(&mut stack);
+        while let Some(mut node) = stack.pop() {
+            node.take_children(&mut stack);
+        }
+    }
+}
+
+impl GreenNode {
+    fn take_children(&mut self, stack: &mut Vec<GreenNode>) {
+        let Some(children) = Arc::get_mut(&mut self.0) else {
+            return;
+        };
+        for child in children.iter_mut().filter(|c| matches!(c, GreenElement::Node(_))) {
+            let hole = GreenElement::Token(GreenToken::new(SyntaxKind::Error, 0));
+            if let GreenElement::Node(node) = std::mem::replace(child, hole) {
+                stack.push(node);
+            }
+        }
+    }
+}
+
+impl fmt::Debug for GreenNode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        enum Step<'a> {
+            Element(&'a GreenElement),
+            Node(&'a GreenNode),
+            Text(&'static str),
+        }
+        let mut stack = vec![Step::Node(self)];
+        while let Some(step) = stack.pop() {
+            match step {
+                Step::Element(GreenElement::Token(token)) => token.fmt(f)?,
+                Step::Element(GreenElement::Node(node)) | Step::Node(node) => {
+                    write!(f, "{:?}@{} [", node.kind(), node.width())?;
+                    stack.push(Step::Text("]"));
+                    for (i, child) in node.children().iter().enumerate().rev() {
+                        stack.push(Step::Element(child));
+                        if i > 0 {
+                            stack.push(Step::Text(", "));
+                        }
+                    }
+                }
+                Step::Text(text) => f.write_str(text)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl GreenElement {
+    pub fn kind(&self) -> SyntaxKind {
+        match self {
+            GreenElement::Node(node) => node.kind(),
+            GreenElement::Token(token) => token.kind(),
+        }
+    }
+
+    pub fn has_error(&self) -> bool {
+        self.code().is_some()
+    }
+
+    /// The code of a problem in this element, if there is one.
+    fn code(&self) -> Option<ErrorCode> {
+        match self {
+            GreenElement::Node(node) => node.header().error,
+            GreenElement::Token(token) => token.error,
+        }
+    }
+
+    pub fn as_node(&self) -> Option<&GreenNode> {
+        match self {
+            GreenElement::Node(node) => Some(node),
+            GreenElement::Token(_) => None,
+        }
+    }
+
+    pub fn as_token(&self) -> Option<&GreenToken> {
+        match self {
+            GreenElement::Node(_) => None,
+            GreenElement::Token(token) => Some(token),
+        }
+    }
+}
+
+impl Width for GreenElement {
+    fn width(&self) -> u32 {
+        match self {
+            GreenElement::Node(node) => node.width(),
+            GreenElement::Token(token) => token.width(),
+        }
+    }
+}
+
+impl fmt::Debug for GreenElement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GreenElement::Node(node) => node.fmt(f),
+            GreenElement::Token(token) => token.fmt(f),
+        }
+    }
+}
+
+/// Builds a tree bottom-up, in the order its tokens appear. Every finished node is looked up by
+/// its kind and children first, so that a node that occurs more than once, e.g. `"id": 1,` or a
+/// whole object of the same shape, is allocated only once.
+#[derive(Default)]
+pub struct GreenBuilder {
+    /// The children of all open nodes, innermost last.
+    children: Vec<GreenElement>,
+    /// The open nodes, with where their children start.
+    parents: Vec<(SyntaxKind, usize)>,
+    cache: HashMap<u64, GreenNode, BuildHasherDefault<Prehashed>>,
+}
+
+impl GreenBuilder {
+    pub fn start_node(&mut self, kind: SyntaxKind) {
+        self.parents.push((kind, self.children.len()));
+    }
+
+    pub fn token(&mut self, token: GreenToken) {
+        self.children.push(GreenElement::Token(token));
+    }
+
+    pub fn finish_node(&mut self) {
+        let (kind, first) = self.parents.pop().expect("a node is open");
+        let node = self.intern(kind, first);
+        self.children.push(GreenElement::Node(node));
+    }
+
+    /// The single element that was built, once all nodes are finished.
+    pub fn finish(mut self) -> GreenElement {
+        assert!(self.parents.is_empty() && self.children.len() == 1, "exactly one element is built");
+        self.children.pop().expect("one element")
+    }
+
+    fn intern(&mut self, kind: SyntaxKind, first: usize) -> GreenNode {
+        let children = &self.children[first..];
+        let mut hasher = Prehashed::default();
+        kind.hash(&mut hasher);
+        for child in children {
+            match child {
+                GreenElement::Node(node) => node.address().hash(&mut hasher),
+                GreenElement::Token(token) => token.hash(&mut hasher),
+            }
+        }
+        let hash = hasher.finish();
+        // Children are interned before their parents, so two equal children are the same node
+        // and comparing one level suffices.
+        let same = |node: &GreenNode| {
+            node.kind() == kind
+                && node.children().len() == children.len()
+                && node.children().iter().zip(children).all(|(a, b)| match (a, b) {
+                    (GreenElement::Node(a), GreenElement::Node(b)) => Arc::ptr_eq(&a.0, &b.0),
+                    (GreenElement::Token(a), GreenElement::Token(b)) => a == b,
+                    _ => false,
+                })
+        };
+        if let Some(node) = self.cache.get(&hash) {
+            if same(node) {
+                let node = node.clone();
+                self.children.truncate(first);
+                return node;
+            }
+        }
+        let node = GreenNode::drain(kind, &mut self.children, first);
+        // On a collision, the node that is already cached stays.
+        self.cache.entry(hash).or_insert_with(|| node.clone());
+        node
+    }
+}
+
+/// A fast hasher for the cache, which hashes small integers and pointers only. Pointers are aligned
+/// and close together, so the result is mixed at the end to spread them over all bits; the table
+/// picks buckets by the low ones.
+#[derive(Default)]
+struct Prehashed(u64);
+
+impl Hasher for Prehashed {
+    fn finish(&self) -> u64 {
+        let mut h = self.0;
+        h ^= h >> 33;
+        h = h.wrapping_mul(0xff51_afd7_ed55_8ccd);
+        h ^= h >> 33;
+        h
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.write_u64(byte as u64);
+        }
+    }
+
+    fn write_u64(&mut self, n: u64) {
+        self.0 = (self.0.rotate_left(5) ^ n).wrapping_mul(0x51_7c_c1_b7_27_22_0a_95);
+    }
+
+    fn write_u32(&mut self, n: u32) {
+        self.write_u64(n as u64);
+    }
+
+    fn write_u8(&mut self, n: u8) {
+        self.write_u64(n as u64);
+    }
+
+    fn write_usize(&mut self, n: usize) {
+        self.write_u64(n as u64);
+    }
+}
+
+/// Walks the tokens in order, with an explicit stack so that deep trees do not overflow. Empty
+/// tokens, i.e. missing values, are left out.
+impl Linearize for GreenNode {
+    fn linearize<'a>(&self, source: &'a str, buf: &mut LinearizeBuffer<'a>) {
+        let mut offset = 0;
+        let mut stack = vec![self.children().iter()];
+        while let Some(children) = stack.last_mut() {
+            match children.next() {
+                Some(GreenElement::Node(node)) => stack.push(node.children().iter()),
+                Some(GreenElement::Token(token)) => {
+                    let end = offset + token.width as usize;
+                    if end > offset {
+                        buf.push((token.kind, &source[offset..end]));
+                    }
+                    offset = end;
+                }
+                None => {
+                    stack.pop();
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse, parse_with, ParseOptions};
+
+    #[test]
+    fn test_shares_identical_subtrees() {
+        let source = parse("[{\"a\": [1, 2]}, {\"a\": [3, 4]}, {\"b\": [5, 6]}]".to_string());
+        let array = source.dom.children().iter().find_map(GreenElement::as_node).unwrap();
+        let items: Vec<_> = array.children().iter().filter_map(GreenElement::as_node).collect();
+        // Tokens store no text, so objects of the same shape are one node.
+        let value = |item: &GreenNode| item.children().iter().find_map(GreenElement::as_node).unwrap().address();
+        assert_eq!(value(items[0]), value(items[1]));
+        assert_eq!(value(items[0]), value(items[2]));
+        // The last item has no comma, so it is a node of its own, but its object is shared.
+        assert_ne!(items[1].address(), items[2].address());
+        let clone = source.dom.clone();
+        assert_eq!(clone.address(), source.dom.address());
+    }
+
+    /// Trees far deeper than the call stack allows can be compared, printed and dropped.
+    #[test]
+    fn test_deep_trees() {
+        let depth = 30_000;
+        let text = format!("{}1{}", "[".repeat(depth), "]".repeat(depth));
+        let options = ParseOptions { max_depth: usize::MAX, ..ParseOptions::default() };
+        let (a, b) = (parse_with(text.clone(), options).dom, parse_with(text, options).dom);
+        assert!(a == b && !Arc::ptr_eq(&a.0, &b.0));
+        assert!(format!("{:?}", a).starts_with("Root@60001 [Array@60001 [LeftBracket@1, Item@59999 [Array@59999 ["));
+        let text = format!("{}12{}", "[".repeat(depth), "]".repeat(depth));
+        assert!(a != parse_with(text, options).dom);
+    }
+
+    /// Reports the memory of the tree per byte of input for a few shapes of document. Members of
+    /// the same shape are shared, so the typical document takes less memory than its text, and
+    /// even one where nothing repeats stays within a small multiple of it.
+    #[test]
+    fn test_memory_per_byte() {
+        let items: Vec<_> = (0..2000)
+            .map(|i| format!("  {{\"id\": {}, \"name\": \"item {}\", \"score\": {}.5, \"tags\": [true, null, \"x\"]}}", i, i, i))
+            .collect();
+        let pairs: Vec<_> = (0..2000).map(|i| format!("  \"key{}\": [{}, \"value\"]", i, i)).collect();
+        let documents = [
+            ("multi", include_str!("../tests/data/multi.json").to_string(), 17.0),
+            ("array-of-objects", format!("[\n{}\n]\n", items.join(",\n")), 0.25),
+            ("large-object", format!("{{\n{}\n}}\n", pairs.join(",\n")), 0.65),
+            ("deep-nesting", format!("{}1{}", "[{\"a\": ".repeat(1000), "}]".repeat(1000)), 36.0),
+        ];
+        for (name, text, max) in documents {
+            let options = ParseOptions { max_depth: usize::MAX, ..ParseOptions::default() };
+            let source = parse_with(text.clone(), options);
+            let per_byte = source.dom.memory() as f64 / text.len() as f64;
+            println!("{:<18} {:>8} bytes {:>8} bytes of tree {:>6.2} per byte", name, text.len(), source.dom.memory(), per_byte);
+            assert!(per_byte < max, "{} takes {:.2} bytes per byte", name, per_byte);
+        }
+    }
+}
@@ -0,0 +1,222 @@
+use crate::buffer::Buffer;
+use crate::escape;
+use crate::options::ParseOptions;
+use crate::parse::{recover, TryParse};
+use crate::range::TextRange;
+use crate::syntax::SyntaxKind;
+use crate::tokens::{is_identifier_part, is_identifier_start, Boolean, Node, Null, Number};
+use crate::trivia;
+
+/// A token produced by [`lex`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Token<'a> {
+    pub kind: SyntaxKind,
+    pub range: TextRange,
+    pub text: &'a str,
+}
+
+/// Splits `text` into tokens without building a tree, see [`lex_with`].
+pub fn lex(text: &str) -> impl Iterator<Item = Token<'_>> {
+    lex_with(text, ParseOptions::default())
+}
+
+/// Splits `text` into the tokens that the parser produces, trivia included. Lexing never fails:
+/// whatever cannot start a token becomes an [`SyntaxKind::Error`] token that ends where the parser
+/// would resume, and the tokens always cover the whole input.
+///
+/// For well-formed input the tokens match [`Source::tokens`](crate::Source::tokens). The lexer
+/// only tracks whether it is at an object key, so a malformed document may be split differently,
+/// e.g. an unclosed container is not swallowed as a whole and trailing content is not one token.
+///
+/// ```
+/// use lst::SyntaxKind;
+/// let kinds: Vec<_> = lst::lex("{\"a\": tru} // c").map(|t| t.kind).collect();
+/// assert_eq!(kinds, vec![
+///     SyntaxKind::LeftBrace,
+///     SyntaxKind::String,
+///     SyntaxKind::Colon,
+///     SyntaxKind::Whitespace,
+///     SyntaxKind::Error,
+///     SyntaxKind::RightBrace,
+///     SyntaxKind::Whitespace,
+///     SyntaxKind::LineComment,
+/// ]);
+/// ```
+pub fn lex_with(text: &str, options: ParseOptions) -> impl Iterator<Item = Token<'_>> {
+    let mut lexer = Lexer { text, options, offset: 0, objects: Vec::new(), key: false };
+    std::iter::from_fn(move || lexer.next())
+}
+
+struct Lexer<'a> {
+    text: &'a str,
+    options: ParseOptions,
+    offset: u32,
+    /// For every open container, whether it is an object.
+    objects: Vec<bool>,
+    /// Whether the next token starts an object key.
+    key: bool,
+}
+
+impl<'a> Lexer<'a> {
+    fn next(&mut self) -> Option<Token<'a>> {
+        let rest = &self.text[self.offset as usize..];
+        if rest.is_empty() {
+            return None;
+        }
+        let (kind, len) = token(rest, self.options, self.key);
+        match kind {
+            SyntaxKind::LeftBrace | SyntaxKind::LeftBracket => self.objects.push(kind == SyntaxKind::LeftBrace),
+            SyntaxKind::RightBrace | SyntaxKind::RightBracket => {
+                self.objects.pop();
+            }
+            _ => {}
+        }
+        if !kind.is_trivia() {
+            self.key = match kind {
+                SyntaxKind::LeftBrace => true,
+                SyntaxKind::Comma => self.objects.last() == Some(&true),
+                _ => false,
+            };
+        }
+        let range = TextRange::at(self.offset, len);
+        self.offset = range.end;
+        Some(Token { kind, range, text: &self.text[std::ops::Range::from(range)] })
+    }
+}
+
+/// The kind and length of the token at the start of `rest`, which is not empty. `key` is whether
+/// an object key is expected, where JSON5 allows identifiers and the parser recovers up to `:`.
+pub(crate) fn token(rest: &str, options: ParseOptions, key: bool) -> (SyntaxKind, u32) {
+    if let Some((kind, len)) = trivia::next(rest, options.json5()) {
+        return (kind.into(), len);
+    }
+    let json5 = options.json5();
+    let c = rest.chars().next().expect("rest is not empty");
+    let kind = match c {
+        '{' => SyntaxKind::LeftBrace,
+        '}' => SyntaxKind::RightBrace,
+        '[' => SyntaxKind::LeftBracket,
+        ']' => SyntaxKind::RightBracket,
+        ':' => SyntaxKind::Colon,
+        ',' => SyntaxKind::Comma,
+        _ => SyntaxKind::Error,
+    };
+    if kind != SyntaxKind::Error {
+        return (kind, 1);
+    }
+    let buffer = Buffer::new(rest, options);
+    let len = match c {
+        '"' => escape::scan(rest, json5, |_, _, _| {}).map(|len| (SyntaxKind::String, len)),
+        '\'' if json5 => escape::scan(rest, json5, |_, _, _| {}).map(|len| (SyntaxKind::String, len)),
+        _ if key && json5 && is_identifier_start(c) => {
+            Some((SyntaxKind::Identifier, (rest.len() - rest.trim_start_matches(is_identifier_part).len()) as u32))
+        }
+        _ if key => None,
+        '+' | '.' | 'I' | 'N' if json5 => Node::<Number>::try_parse(buffer).ok().map(|n| (SyntaxKind::Number, n.0)),
+        '-' | '0'..='9' => Node::<Number>::try_parse(buffer).ok().map(|n| (SyntaxKind::Number, n.0)),
+        't' | 'f' => Node::<Boolean>::try_parse(buffer).ok().map(|n| (SyntaxKind::Boolean, n.0)),
+        'n' => Node::<Null>::try_parse(buffer).ok().map(|n| (SyntaxKind::Null, n.0)),
+        _ => None,
+    };
+    len.unwrap_or_else(|| {
+        let stop: &[char] = if key { &[':'] } else { &[] };
+        (SyntaxKind::Error, recover(rest, stop).max(c.len_utf8() as u32))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse_with, Dialect};
+
+    fn kinds(text: &str, dialect: Dialect) -> Vec<(SyntaxKind, &str)> {
+        lex_with(text, dialect.into()).map(|t| (t.kind, t.text)).collect()
+    }
+
+    #[test]
+    fn test_lex() {
+        use SyntaxKind::*;
+        assert_eq!(kinds("{a: 'b', \"c\": [.5, +1, NaN]}", Dialect::Json5), vec![
+            (LeftBrace, "{"),
+            (Identifier, "a"),
+            (Colon, ":"),
+            (Whitespace, " "),
+            (String, "'b'"),
+            (Comma, ","),
+            (Whitespace, " "),
+            (String, "\"c\""),
+            (Colon, ":"),
+            (Whitespace, " "),
+            (LeftBracket, "["),
+            (Number, ".5"),
+            (Comma, ","),
+            (Whitespace, " "),
+            (Number, "+1"),
+            (Comma, ","),
+            (Whitespace, " "),
+            (Number, "NaN"),
+            (RightBracket, "]"),
+            (RightBrace, "}"),
+        ]);
+        // The same input in plain JSON is mostly errors, which end where the parser would resume.
+        assert_eq!(kinds("{a: 'b'}", Dialect::Json), vec![
+            (LeftBrace, "{"),
+            (Error, "a"),
+            (Colon, ":"),
+            (Whitespace, " "),
+            (Error, "'b'"),
+            (RightBrace, "}"),
+        ]);
+        assert_eq!(kinds("[\"open, 01, -, @@ ]", Dialect::Jsonc), vec![
+            (LeftBracket, "["),
+            (Error, "\"open, 01, -, @@ ]"),
+        ]);
+        assert_eq!(kinds("[01, -, @@ ]/* x", Dialect::Jsonc), vec![
+            (LeftBracket, "["),
+            (Error, "01"),
+            (Comma, ","),
+            (Whitespace, " "),
+            (Error, "-"),
+            (Comma, ","),
+            (Whitespace, " "),
+            (Error, "@@"),
+            (Whitespace, " "),
+            (RightBracket, "]"),
+            (BlockComment, "/* x"),
+        ]);
+    }
+
+    /// On well-formed input, the lexer and the parser agree on every token.
+    #[test]
+    fn test_matches_parser() {
+        let documents = [
+            (include_str!("../tests/data/multi.json"), Dialect::Json),
+            ("// c\n{\"a\": [1, {\"b\": null},], /* d */ \"e\": -2.5e3}\n", Dialect::Jsonc),
+            ("{unquoted: 'x', $id: 0xFF, lead: .5, inf: -Infinity, \"s\": [+1, 'it\\'s',],}", Dialect::Json5),
+        ];
+        for (text, dialect) in documents {
+            let source = parse_with(text.to_string(), dialect.into());
+            assert!(!source.has_error(), "{}", text);
+            let parsed: Vec<_> = source.tokens().map(|(kind, range, text)| Token { kind, range, text }).collect();
+            assert_eq!(lex_with(text, dialect.into()).collect::<Vec<_>>(), parsed);
+        }
+    }
+
+    /// Whatever the input, the tokens are non-empty and cover it without gaps.
+    #[test]
+    fn test_covers_input() {
+        let text = "// a\n{ /* b */ \"k\" /* c */: [1, // d\n 2 /* e */], 'é': {x: tru // f\n}}} ] \"\\q\" 0x 1e /* g";
+        for dialect in [Dialect::Json, Dialect::Jsonc, Dialect::Json5] {
+            for end in (0..=text.len()).filter(|&i| text.is_char_boundary(i)) {
+                let prefix = &text[..end];
+                let mut offset = 0;
+                for token in lex_with(prefix, dialect.into()) {
+                    assert_eq!(token.range.start, offset, "{:?}", prefix);
+                    assert!(!token.text.is_empty());
+                    offset = token.range.end;
+                }
+                assert_eq!(offset as usize, prefix.len());
+            }
+        }
+    }
+}
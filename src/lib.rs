@@ -1,34 +1,64 @@
 #![allow(unused)]
 
-use std::{any::Any, fmt::Debug};
 use buffer::Buffer;
-use parse::TryParse;
-use source::Source;
-use tokens::Node;
-use tokens::Offset;
-use tokens::Value;
+pub use source::Source;
 use width::Width;
+mod green;
 mod tokens;
 mod width;
 mod source;
 mod buffer;
+mod options;
 mod parse;
+mod escape;
+mod trivia;
+mod range;
+mod line_index;
+mod diagnostic;
+mod syntax;
+pub mod ast;
+mod pointer;
+mod edit;
+mod document;
+pub mod assist;
+mod format;
+mod minify;
+mod lexer;
+#[cfg(test)]
+mod test_support;
+
+pub use diagnostic::{Diagnostic, Severity};
+pub use document::Document;
+pub use edit::{apply_edits, EditError, TextEdit};
+pub use format::{format, format_range, FormatOptions, Indent, TrailingCommas};
+pub use lexer::{lex, lex_with, Token};
+pub use line_index::{LineIndex, Position};
+pub use minify::minify;
+pub use options::{Dialect, ParseOptions, Policy};
+pub use parse::{ErrorCode, ParseError};
+pub use range::TextRange;
+pub use syntax::{SyntaxKind, SyntaxNode, SyntaxToken};
 
 
+/// Parses `content` as JSON with comments, see [`Dialect::Jsonc`].
 pub fn parse(content: String) -> Source {
-    let buf = Buffer { content: &content };
-    let dom = Offset::<Node<Value>>::try_parse(buf).expect("error is infallible");
-    Source { content, dom }
+    parse_with(content, ParseOptions::default())
+}
+
+pub fn parse_with(content: String, options: ParseOptions) -> Source {
+    let dom = parse::Parser::new(Buffer::new(&content, options)).root();
+    Source { content, dom, options }
 }
 
-pub type LinearizeBuffer<'a> = Vec<(&'a dyn Debug, &'a str)>;
+/// Every token of a tree in document order, with its kind.
+pub type LinearizeBuffer<'a> = Vec<(SyntaxKind, &'a str)>;
 
 pub trait Linearize {
-    fn linearize<'a>(&'a self, source: &'a str, buf: &mut LinearizeBuffer<'a>);
+    fn linearize<'a>(&self, source: &'a str, buf: &mut LinearizeBuffer<'a>);
 }
 
 impl<T: Linearize> Linearize for Option<T> {
-    fn linearize<'a>(&'a self, source: &'a str, buf: &mut LinearizeBuffer<'a>) {
+    fn linearize<'a>(&self, source: &'a str, buf: &mut LinearizeBuffer<'a>) {
         if let Some(v) = self {
             v.linearize(source, buf);
         }
@@ -45,7 +75,7 @@ mod tests {
         let s = parse(s.to_string());
         assert!(s.has_error());
         let s = s.linearize_tokens();
-        assert_eq!(s, vec!["//hello\n", "{\n\n"]);
+        assert_eq!(s, vec!["//hello\n", "{", "\n\n"]);
 
         let s = "//hello\n{\n\n}";
         let s = parse(s.to_string());
@@ -54,13 +84,75 @@ mod tests {
         assert_eq!(s, vec!["//hello\n", "{", "\n\n", "}"]);
     }
 
+    #[test]
+    fn test_block_comment_tokens() {
+        let s = parse("/* a */[1, /* b\n */ 2] // c\n".to_string());
+        assert!(!s.has_error());
+        let kinds = s.linearize();
+        assert_eq!(&kinds[..3], &[(SyntaxKind::BlockComment, "/* a */"), (SyntaxKind::LeftBracket, "["), (SyntaxKind::Number, "1")]);
+        assert!(kinds.contains(&(SyntaxKind::BlockComment, "/* b\n */")));
+    }
+
+    #[test]
+    fn test_json5() {
+        let text = "// config\n{\n  unquoted: 'single \\' quote',\n  $id: 0xFF,\n  lead: .5, trail: 5., plus: +1,\n  inf: -Infinity, nan: NaN,\n  \"s\": [1, 2,],\n}\n";
+        let s = parse_with(text.to_string(), Dialect::Json5.into());
+        assert!(!s.has_error());
+        assert!(s.diagnostics().is_empty());
+        assert_eq!(s.linearize_tokens().concat(), text);
+        assert!(parse(text.to_string()).has_error());
+
+        let object = ast::Object::cast(s.root().first_child().unwrap()).unwrap();
+        assert_eq!(object.keys().collect::<Vec<_>>(), vec!["unquoted", "$id", "lead", "trail", "plus", "inf", "nan", "s"]);
+        let value = ast::StringToken::cast(object.get("unquoted").unwrap()).unwrap();
+        assert_eq!(value.value(), "single ' quote");
+    }
+
+    #[test]
+    fn test_trailing_content() {
+        let s = parse("{} // done\n".to_string());
+        assert!(!s.has_error());
+        assert_eq!(s.linearize_tokens(), vec!["{", "}", " ", "// done\n"]);
+
+        let text = "{\"a\": 1}\n{\"b\": 2}\n";
+        let s = parse(text.to_string());
+        assert!(s.has_error());
+        assert_eq!(s.linearize_tokens().concat(), text);
+        let d: Vec<_> = s.diagnostics().iter().map(|d| (d.code, d.range.start, d.range.end)).collect();
+        assert_eq!(d, vec![(ErrorCode::TrailingContent, 9, 17)]);
+        let kinds: Vec<_> = s.root().children().map(|n| n.kind()).collect();
+        assert_eq!(kinds, vec![SyntaxKind::Object, SyntaxKind::Error]);
+        assert_eq!(s.root().children().nth(1).unwrap().text(), "{\"b\": 2}\n");
+    }
+
     #[test]
     fn test_multi() {
         let s = include_str!("../tests/data/multi.json");
         let s = parse(s.to_string());
         assert!(!s.has_error());
         let s = s.linearize_tokens();
-        let tt: Vec<&str> = vec![];
+        let tt: Vec<&str> = vec![
+            "{", "\n  ", "\"a\"", ":", " ", "\"x\"", ",",
+            "\n  ", "\"b\"", ":", " ", "1", ",",
+            "\n  ", "\"c\"", ":", " ", "true", ",",
+            "\n  ", "\"d\"", ":", " ", "[", "1", ",", " ", "\"two\"", ",", " ", "true", ",", " ",
+            "{", " ", "\"e\"", ":", " ", "\"f\"", " ", "}", ",", " ", "3.0", "]", ",",
+            "\n  ", "\"e\"", ":", " ", "{", "\n    ", "\"f\"", ":", " ", "\"g\"", "\n  ", "}",
+            "\n", "}",
+        ];
         assert_eq!(s, tt);
     }
+
+    #[test]
+    fn test_recovery_keeps_siblings() {
+        let s = "{\"a\": tru, \"b\": [1 2, x, , 3], \"c\" 4}";
+        let s = parse(s.to_string());
+        assert!(s.has_error());
+        let s = s.linearize_tokens();
+        assert_eq!(s, vec![
+            "{", "\"a\"", ":", " ", "tru", ",",
+            " ", "\"b\"", ":", " ", "[", "1", " ", "2", ",", " ", "x", ",", " ", ",", " ", "3", "]", ",",
+            " ", "\"c\"", " ", "4", "}",
+        ]);
+    }
 }
@@ -0,0 +1,77 @@
+/// A zero-based line/column position. `column` counts UTF-8 bytes from the start of the line,
+/// `column_utf16` counts UTF-16 code units, which is what LSP clients expect by default.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Position {
+    pub line: u32,
+    pub column: u32,
+    pub column_utf16: u32,
+}
+
+/// Maps byte offsets to line/column positions.
+#[derive(Debug)]
+pub struct LineIndex {
+    /// Byte offset at which each line starts.
+    line_starts: Vec<u32>,
+    /// The byte offset of each non-ASCII char, and how many fewer UTF-16 code units than UTF-8
+    /// bytes it and the ones before it take.
+    non_ascii: Vec<(u32, u32)>,
+}
+
+impl LineIndex {
+    pub fn new(text: &str) -> Self {
+        let mut line_starts = vec![0];
+        line_starts.extend(text.match_indices('\n').map(|(i, _)| i as u32 + 1));
+        let mut saved = 0;
+        let non_ascii = text
+            .char_indices()
+            .filter(|(_, c)| !c.is_ascii())
+            .map(|(i, c)| {
+                saved += (c.len_utf8() - c.len_utf16()) as u32;
+                (i as u32, saved)
+            })
+            .collect();
+        Self { line_starts, non_ascii }
+    }
+
+    /// How many fewer UTF-16 code units than UTF-8 bytes the text before `offset` takes.
+    fn saved(&self, offset: usize) -> usize {
+        match self.non_ascii.partition_point(|&(i, _)| (i as usize) < offset) {
+            0 => 0,
+            n => self.non_ascii[n - 1].1 as usize,
+        }
+    }
+
+    /// `offset` is clamped to `text`, and rounded down to the nearest char boundary.
+    pub fn position(&self, text: &str, offset: u32) -> Position {
+        let mut offset = (offset as usize).min(text.len());
+        while !text.is_char_boundary(offset) {
+            offset -= 1;
+        }
+        let line = self.line_starts.partition_point(|&start| start as usize <= offset) - 1;
+        let start = self.line_starts[line] as usize;
+        let column_utf16 = offset - start - (self.saved(offset) - self.saved(start));
+        Position {
+            line: line as u32,
+            column: (offset - start) as u32,
+            column_utf16: column_utf16 as u32,
+        }
+    }
+
+    /// The inverse of [`LineIndex::position`] for UTF-16 columns. Positions past the end of a line
+    /// are clamped to the end of that line, and lines past the end to the end of the text.
+    pub fn offset_utf16(&self, text: &str, line: u32, column_utf16: u32) -> u32 {
+        let Some(&start) = self.line_starts.get(line as usize) else {
+            return text.len() as u32;
+        };
+        let mut offset = start;
+        let mut column = 0;
+        for c in text[start as usize..].chars() {
+            if c == '\n' || column >= column_utf16 {
+                break;
+            }
+            column += c.len_utf16() as u32;
+            offset += c.len_utf8() as u32;
+        }
+        offset
+    }
+}
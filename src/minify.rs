@@ -0,0 +1,220 @@
+use std::borrow::Cow;
+use crate::diagnostic::{Diagnostic, Severity};
+use crate::edit::quote;
+use crate::escape;
+use crate::line_index::LineIndex;
+use crate::options::ParseOptions;
+use crate::parse::{self, ErrorCode};
+use crate::range::TextRange;
+use crate::source::Source;
+use crate::tokens::{self, Array, Key, Node, Object, Value};
+use crate::width::Width;
+
+/// Drops all whitespace, comments and trailing commas, and writes strict JSON: unquoted keys,
+/// single-quoted strings and JSON5 numbers are converted. Refuses a document with malformed parts
+/// or trailing content, and returns the first problem as an error instead.
+///
+/// ```
+/// let source = lst::parse("{\n  // name\n  \"a\": [1, 2,],\n}".to_string());
+/// assert_eq!(lst::minify(&source).unwrap(), "{\"a\":[1,2]}");
+/// assert!(lst::minify(&lst::parse("[1 2]".to_string())).is_err());
+/// ```
+pub fn minify(source: &Source) -> Result<String, Diagnostic> {
+    let text = source.content.as_str();
+    let mut minifier = Minifier { text, options: source.options, out: String::with_capacity(text.len()) };
+    let root = source.tree();
+    let result = minifier.value(&root.value.1, root.value.0).and_then(|()| match root.trailing.1 {
+        Some(Node(width, tokens::Invalid(code))) => {
+            let start = root.value.width() + root.trailing.0;
+            let len = text[start as usize..(start + width) as usize].trim_end().len();
+            Err(minifier.error(code, TextRange::at(start, len as u32)))
+        }
+        None => Ok(()),
+    });
+    result.map(|()| minifier.out).map_err(|(code, message, range)| {
+        let index = LineIndex::new(text);
+        Diagnostic {
+            code,
+            severity: Severity::Error,
+            message,
+            range,
+            start: index.position(text, range.start),
+            end: index.position(text, range.end),
+        }
+    })
+}
+
+type Error = (ErrorCode, Cow<'static, str>, TextRange);
+
+struct Minifier<'a> {
+    text: &'a str,
+    options: ParseOptions,
+    out: String,
+}
+
+impl Minifier<'_> {
+    fn value(&mut self, node: &Node<Value>, offset: u32) -> Result<(), Error> {
+        let range = TextRange::at(offset, node.width());
+        let literal = &self.text[offset as usize..(offset + node.width()) as usize];
+        match &node.1 {
+            Value::Invalid(code) => return Err(self.error(*code, range)),
+            Value::String(s) => self.string(s, literal, range)?,
+            Value::Number(_) => self.number(literal, range)?,
+            Value::Boolean(_) | Value::Null(_) => self.out.push_str(literal),
+            Value::Object(o) => self.object(o, offset)?,
+            Value::Array(a) => self.array(a, offset)?,
+        }
+        Ok(())
+    }
+
+    fn object(&mut self, object: &Object, mut offset: u32) -> Result<(), Error> {
+        if object.right_brace().1.is_none() {
+            return Err(self.error(ErrorCode::UnclosedObject, TextRange::at(offset, 1)));
+        }
+        self.out.push('{');
+        offset += object.left_brace().width();
+        let pairs = object.pairs();
+        for (i, pair) in pairs.iter().enumerate() {
+            if i > 0 {
+                self.out.push(',');
+            }
+            let key_start = offset + pair.key.0;
+            let range = TextRange::at(key_start, pair.key.1.width());
+            let literal = &self.text[range.start as usize..range.end as usize];
+            match &pair.key.1 .1 {
+                Key::Invalid(code) => return Err(self.error(*code, range)),
+                Key::String(s) => self.string(s, literal, range)?,
+                Key::Identifier(_) => self.out.push_str(&quote(literal)),
+            }
+            offset += pair.key.width();
+            if pair.colon.1.is_none() {
+                return Err((ErrorCode::MissingColon, "Expected ':' after the key".into(), TextRange::empty(range.end)));
+            }
+            self.out.push(':');
+            offset += pair.colon.width();
+            self.value(&pair.value.1, offset + pair.value.0)?;
+            offset += pair.value.width();
+            self.comma(pair.comma.1.is_some(), offset, i + 1 == pairs.len())?;
+            offset += pair.comma.width();
+        }
+        self.out.push('}');
+        Ok(())
+    }
+
+    fn array(&mut self, array: &Array, mut offset: u32) -> Result<(), Error> {
+        if array.right_bracket().1.is_none() {
+            return Err(self.error(ErrorCode::UnclosedArray, TextRange::at(offset, 1)));
+        }
+        self.out.push('[');
+        offset += array.left_bracket().width();
+        let values = array.values();
+        for (i, item) in values.iter().enumerate() {
+            if i > 0 {
+                self.out.push(',');
+            }
+            self.value(&item.item.1, offset + item.item.0)?;
+            offset += item.item.width();
+            self.comma(item.comma.1.is_some(), offset, i + 1 == values.len())?;
+            offset += item.comma.width();
+        }
+        self.out.push(']');
+        Ok(())
+    }
+
+    /// The tree only keeps the code of an error; its message comes from lexing the text again.
+    fn error(&self, code: ErrorCode, range: TextRange) -> Error {
+        let e = parse::error(code, &self.text[range.start as usize..], self.options);
+        (code, e.message().to_string().into(), range)
+    }
+
+    fn comma(&self, present: bool, offset: u32, last: bool) -> Result<(), Error> {
+        if !present && !last {
+            return Err((ErrorCode::MissingComma, "Expected ',' between members".into(), TextRange::empty(offset)));
+        }
+        Ok(())
+    }
+
+    /// A JSON string is copied as-is; a JSON5 one is decoded and quoted again unless it is already
+    /// valid JSON. JSON5 allows raw control characters in strings, and JSON does not.
+    fn string(&mut self, s: &tokens::String, literal: &str, range: TextRange) -> Result<(), Error> {
+        if s.malformed {
+            let mut error = None;
+            escape::scan(literal, self.options.json5(), |at, code, message| {
+                error.get_or_insert((code, message.into(), TextRange::at(range.start + at.start, at.len())));
+            });
+            return Err(error.unwrap_or((ErrorCode::InvalidEscape, "Malformed string".into(), range)));
+        }
+        if self.options.json5() && (literal.starts_with('\'') || literal.bytes().any(|b| b == b'\\' || b < 0x20)) {
+            self.out.push_str(&quote(&escape::unescape(literal, true)));
+        } else {
+            self.out.push_str(literal);
+        }
+        Ok(())
+    }
+
+    /// Rewrites the JSON5 number forms: a leading `+`, a leading or trailing decimal point and hex.
+    fn number(&mut self, literal: &str, range: TextRange) -> Result<(), Error> {
+        let (sign, digits) = match literal.as_bytes()[0] {
+            b'+' => ("", &literal[1..]),
+            b'-' => ("-", &literal[1..]),
+            _ => ("", literal),
+        };
+        if digits == "Infinity" || digits == "NaN" {
+            return Err((ErrorCode::InvalidNumber, format!("{} cannot be written as JSON", literal).into(), range));
+        }
+        self.out.push_str(sign);
+        if let Some(hex) = digits.strip_prefix("0x").or_else(|| digits.strip_prefix("0X")) {
+            let value = u128::from_str_radix(hex, 16)
+                .map_err(|_| (ErrorCode::InvalidNumber, "Hex number is too large".into(), range))?;
+            self.out.push_str(&value.to_string());
+            return Ok(());
+        }
+        if digits.starts_with('.') {
+            self.out.push('0');
+        }
+        let exponent = digits.find(['e', 'E']).unwrap_or(digits.len());
+        let (mantissa, exponent) = digits.split_at(exponent);
+        self.out.push_str(mantissa.strip_suffix('.').unwrap_or(mantissa));
+        self.out.push_str(exponent);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse, parse_with, Dialect};
+
+    #[test]
+    fn test_minify() {
+        let text = "// config\n{\n  \"a\": [1, 2.5e3, ], /* b */ \"b\": {\"c\" : null},\n  \"d\": \"x\\u0041\"\n}\n";
+        assert_eq!(minify(&parse(text.to_string())).unwrap(), "{\"a\":[1,2.5e3],\"b\":{\"c\":null},\"d\":\"x\\u0041\"}");
+        assert_eq!(minify(&parse(" [ ] ".to_string())).unwrap(), "[]");
+    }
+
+    #[test]
+    fn test_json5() {
+        let source = parse_with("{a: 'it\\'s', 'b\"': [+1, .5, 5., 0x1F, -0XfF, 1.e2]}".to_string(), Dialect::Json5.into());
+        assert_eq!(minify(&source).unwrap(), "{\"a\":\"it's\",\"b\\\"\":[1,0.5,5,31,-255,1e2]}");
+        let source = parse_with("[\"a\tb\", 'c\u{1}d']".to_string(), Dialect::Json5.into());
+        assert_eq!(minify(&source).unwrap(), "[\"a\\tb\",\"c\\u0001d\"]");
+        let source = parse_with("[1, -Infinity]".to_string(), Dialect::Json5.into());
+        let error = minify(&source).unwrap_err();
+        assert_eq!((error.code, error.range), (ErrorCode::InvalidNumber, TextRange::new(4, 13)));
+    }
+
+    #[test]
+    fn test_refuses_malformed() {
+        let error = |text: &str| {
+            let e = minify(&parse(text.to_string())).unwrap_err();
+            (e.code, e.range.start, e.range.end)
+        };
+        assert_eq!(error("{\"a\": tru}"), (ErrorCode::InvalidLiteral, 6, 9));
+        assert_eq!(error("[1 2]"), (ErrorCode::MissingComma, 2, 2));
+        assert_eq!(error("{\"a\" 1}"), (ErrorCode::MissingColon, 4, 4));
+        assert_eq!(error("[\"a\\qb\"]"), (ErrorCode::InvalidEscape, 3, 5));
+        assert_eq!(error("[1] [2] "), (ErrorCode::TrailingContent, 4, 7));
+        let e = minify(&parse("{\n  \"a\": [1,\n".to_string())).unwrap_err();
+        assert_eq!((e.code, e.severity), (ErrorCode::UnclosedObject, Severity::Error));
+    }
+}
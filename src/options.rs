@@ -0,0 +1,96 @@
+use crate::diagnostic::Severity;
+
+/// The flavour of JSON a document is written in. Besides what is lexed, the dialect picks the
+/// default [`Policy`] for each of the relaxations in [`ParseOptions`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Dialect {
+    /// RFC 8259. Comments are lexed as trivia so that the tree stays lossless, but they are errors
+    /// by default, and so are trailing commas.
+    Json,
+    /// JSON with comments, as used by tsconfig and VS Code settings. Trailing commas are warnings by
+    /// default.
+    #[default]
+    Jsonc,
+    /// [JSON5](https://spec.json5.org): identifier keys, single-quoted strings, hex numbers, a
+    /// leading or trailing decimal point, `+`, `Infinity`, `NaN` and trailing commas.
+    Json5,
+}
+
+/// How a construct that is well-formed but not allowed everywhere is reported.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Policy {
+    Allow,
+    Warn,
+    Error,
+}
+
+impl Policy {
+    pub(crate) fn severity(self) -> Option<Severity> {
+        match self {
+            Policy::Allow => None,
+            Policy::Warn => Some(Severity::Warning),
+            Policy::Error => Some(Severity::Error),
+        }
+    }
+}
+
+/// Options for [`parse_with`](crate::parse_with). The policies are enforced by the diagnostics and
+/// never change the tree, so a document parses the same way under any of them:
+///
+/// ```
+/// use lst::{Dialect, ParseOptions, Policy};
+/// let strict = ParseOptions { duplicate_keys: Policy::Error, ..ParseOptions::new(Dialect::Json) };
+/// assert!(lst::parse_with(r#"{"a": 1, "a": 2}"#.to_string(), strict).has_error());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ParseOptions {
+    pub dialect: Dialect,
+    /// `//` and `/* */` comments.
+    pub comments: Policy,
+    /// A comma after the last member of an object or array.
+    pub trailing_commas: Policy,
+    /// A key that appears more than once in the same object, compared after decoding escapes.
+    pub duplicate_keys: Policy,
+    /// Anything but trivia after the top-level value.
+    pub trailing_content: Policy,
+    /// How deeply objects and arrays may nest. A container below this depth is not parsed but
+    /// becomes a single error, so an untrusted document cannot build an arbitrarily deep tree.
+    /// Parsing, diagnostics, writing the text back and dropping the tree do not recurse, but
+    /// formatting, minifying and walking [`SyntaxNode`](crate::SyntaxNode)s do, so raising this far
+    /// beyond the default of 128 needs a correspondingly large stack for those.
+    pub max_depth: usize,
+}
+
+impl ParseOptions {
+    pub fn new(dialect: Dialect) -> Self {
+        let (comments, trailing_commas) = match dialect {
+            Dialect::Json => (Policy::Error, Policy::Error),
+            Dialect::Jsonc => (Policy::Allow, Policy::Warn),
+            Dialect::Json5 => (Policy::Allow, Policy::Allow),
+        };
+        Self {
+            dialect,
+            comments,
+            trailing_commas,
+            duplicate_keys: Policy::Warn,
+            trailing_content: Policy::Error,
+            max_depth: 128,
+        }
+    }
+
+    pub(crate) fn json5(&self) -> bool {
+        self.dialect == Dialect::Json5
+    }
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        Self::new(Dialect::default())
+    }
+}
+
+impl From<Dialect> for ParseOptions {
+    fn from(dialect: Dialect) -> Self {
+        Self::new(dialect)
+    }
+}
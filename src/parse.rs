@@ -1,10 +1,11 @@
-use std::convert::Infallible;
 use crate::buffer::Buffer;
-use crate::tokens;
-use crate::tokens::{Array, Boolean, LeftBrace, Number, Object, Pair, RightBrace};
-use crate::tokens::Node;
-use crate::tokens::Offset;
-use crate::tokens::Value;
+use crate::escape;
+use crate::green::{GreenBuilder, GreenElement, GreenNode, GreenToken};
+use crate::lexer;
+use crate::options::ParseOptions;
+use crate::syntax::SyntaxKind;
+use crate::tokens::{is_identifier_part, is_identifier_start, Boolean, Node, Null, Number};
+use crate::trivia;
 use crate::width::Width;
 
 pub trait TryParse: Sized {
@@ -12,131 +13,734 @@ pub trait TryParse: Sized {
     fn try_parse(content: Buffer<'_>) -> Result<Self, Self::Error>;
 }
 
-impl<T: TryParse> TryParse for Option<T> {
-    type Error = Infallible;
-    fn try_parse(content: Buffer<'_>) -> Result<Self, Infallible> {
-        Ok(T::try_parse(content).ok())
-    }
-}
-
-impl TryParse for Node<tokens::String> {
+/// Lexes an RFC 8259 number: `-? (0 | [1-9][0-9]*) (. [0-9]+)? ([eE] [+-]? [0-9]+)?`. A number that is
+/// directly followed by more number-like characters is rejected as a whole instead of being split
+/// into two values.
+impl TryParse for Node<Number> {
     type Error = ParseError;
-    fn try_parse(mut content: Buffer<'_>) -> Result<Self, ParseError> {
-        let mut chars = content.chars();
-        let Some(c) = chars.next() else {
-            return Err(ParseError::new("String must contain a character"));
-        };
-        if c != '"' {
-            return Err(ParseError::new("String must start with '\"'"));
-        }
-        let mut len = c.width();
-        while let Some(c) = chars.next() {
-            len += c.width();
-            if c == '\\' {
-                chars.next();
-                len += c.width();
-            } else if c == '"' {
-                // content.slice_start(len);
-                return Ok(Node(len, tokens::String));
+    fn try_parse(content: Buffer<'_>) -> Result<Self, ParseError> {
+        if content.options.json5() {
+            return json5_number(content.as_bytes());
+        }
+        let bytes = content.as_bytes();
+        let digits = |from: usize| bytes[from..].iter().take_while(|b| b.is_ascii_digit()).count();
+        let mut len = 0;
+        if bytes.first() == Some(&b'-') {
+            len += 1;
+        }
+        match bytes.get(len) {
+            Some(b'0') => len += 1,
+            Some(b'1'..=b'9') => len += digits(len),
+            _ if len == 1 => return Err(ParseError::new(ErrorCode::InvalidNumber, "Expected a digit after '-'")),
+            _ => return Err(ParseError::new(ErrorCode::InvalidNumber, "Number must start with a digit")),
+        }
+        if bytes.get(len) == Some(&b'.') {
+            len += 1;
+            match digits(len) {
+                0 => return Err(ParseError::new(ErrorCode::MissingFraction, "Expected a digit after the decimal point")),
+                n => len += n,
+            }
+        }
+        if let Some(b'e' | b'E') = bytes.get(len) {
+            len += 1;
+            if let Some(b'+' | b'-') = bytes.get(len) {
+                len += 1;
+            }
+            match digits(len) {
+                0 => return Err(ParseError::new(ErrorCode::MissingExponent, "Expected a digit in the exponent")),
+                n => len += n,
             }
         }
-        Err(ParseError::new("String must end with '\"'"))
+        number_end(bytes, len)
     }
 }
 
-impl TryParse for Node<Number> {
-    type Error = ParseError;
-    fn try_parse(mut content: Buffer<'_>) -> Result<Self, ParseError> {
-        let mut chars = content.chars();
-        let Some(c) = chars.next() else {
-            return Err(ParseError::new("Number must contain at least one digit"));
+/// Lexes a JSON5 number, which adds a leading `+`, hexadecimal integers, a leading or trailing
+/// decimal point, `Infinity` and `NaN` to RFC 8259.
+fn json5_number(bytes: &[u8]) -> Result<Node<Number>, ParseError> {
+    let digits = |from: usize| bytes[from..].iter().take_while(|b| b.is_ascii_digit()).count();
+    let mut len = 0;
+    if let Some(b'+' | b'-') = bytes.first() {
+        len += 1;
+    }
+    let rest = &bytes[len..];
+    if rest.starts_with(b"Infinity") || rest.starts_with(b"NaN") {
+        return number_end(bytes, len + if rest[0] == b'I' { 8 } else { 3 });
+    }
+    if let [b'0', b'x' | b'X', ..] = rest {
+        len += 2;
+        return match bytes[len..].iter().take_while(|b| b.is_ascii_hexdigit()).count() {
+            0 => Err(ParseError::new(ErrorCode::InvalidNumber, "Expected a hex digit after 0x")),
+            n => number_end(bytes, len + n),
         };
-        let mut len = c.width();
-        if c < '0' || c > '9' {
-            return Err(ParseError::new("Number must start with a digit"));
-        }
-        let leading_zero = c == '0';
-        let mut dot_position = -1;
-        while let Some(c) = chars.next() {
-            len += c.width();
-            if c == '.' {
-                if dot_position > -1 {
-                    return Err(ParseError::new("Encountered a second . in a number"));
-                }
-                dot_position = len as i32 - 1;
-            } else if !c.is_digit(10) {
-                len -= c.width();
-                break;
-            }
+    }
+    let integer = match rest.first() {
+        Some(b'0') => 1,
+        Some(b'1'..=b'9') => digits(len),
+        Some(b'.') => 0,
+        _ if len == 1 => return Err(ParseError::new(ErrorCode::InvalidNumber, "Expected a digit after the sign")),
+        _ => return Err(ParseError::new(ErrorCode::InvalidNumber, "Number must start with a digit")),
+    };
+    len += integer;
+    if bytes.get(len) == Some(&b'.') {
+        len += 1;
+        match digits(len) {
+            0 if integer == 0 => return Err(ParseError::new(ErrorCode::MissingFraction, "Expected a digit around the decimal point")),
+            n => len += n,
+        }
+    }
+    if let Some(b'e' | b'E') = bytes.get(len) {
+        len += 1;
+        if let Some(b'+' | b'-') = bytes.get(len) {
+            len += 1;
         }
-        if leading_zero && (dot_position == -1 || dot_position > 1) {
-            return Err(ParseError::new("Number cannot have leading zeros"));
+        match digits(len) {
+            0 => return Err(ParseError::new(ErrorCode::MissingExponent, "Expected a digit in the exponent")),
+            n => len += n,
         }
-        // content.slice_start(len);
-        Ok(Node(len, Number))
+    }
+    number_end(bytes, len)
+}
+
+/// Accepts a number of length `len` unless it runs straight into more number-like characters.
+fn number_end(bytes: &[u8], len: usize) -> Result<Node<Number>, ParseError> {
+    match bytes.get(len) {
+        // Only an integer part of `0` can stop in front of another digit.
+        Some(b'0'..=b'9') => Err(ParseError::new(ErrorCode::LeadingZero, "Number cannot have leading zeros")),
+        Some(b'.') => Err(ParseError::new(ErrorCode::InvalidNumber, "Encountered a second . in a number")),
+        Some(&c) if c.is_ascii_alphanumeric() || c == b'+' || c == b'-' || c == b'_' => {
+            Err(ParseError::new(ErrorCode::InvalidNumber, format!("Unexpected '{}' in number", c as char)))
+        }
+        _ => Ok(Node(len as u32, Number)),
     }
 }
 
 impl TryParse for Node<Boolean> {
     type Error = ParseError;
-    fn try_parse(mut content: Buffer<'_>) -> Result<Self, ParseError> {
+    fn try_parse(content: Buffer<'_>) -> Result<Self, ParseError> {
+        let error = || ParseError::new(ErrorCode::InvalidLiteral, "Boolean must be either true or false");
         if content.starts_with("true") {
-            // content.slice_start(4);
-            Ok(Node(4, Boolean))
+            literal_end(&content, 4, Boolean).ok_or_else(error)
         } else if content.starts_with("false") {
-            // content.slice_start(5);
-            Ok(Node(5, Boolean))
+            literal_end(&content, 5, Boolean).ok_or_else(error)
         } else {
-            Err(ParseError::new("Boolean must be either true or false"))
+            Err(error())
         }
     }
 }
 
+impl TryParse for Node<Null> {
+    type Error = ParseError;
+    fn try_parse(content: Buffer<'_>) -> Result<Self, ParseError> {
+        let error = || ParseError::new(ErrorCode::InvalidLiteral, "Expected null");
+        if content.starts_with("null") {
+            literal_end(&content, 4, Null).ok_or_else(error)
+        } else {
+            Err(error())
+        }
+    }
+}
 
-impl TryParse for Node<Value> {
-    type Error = Infallible;
-    fn try_parse(mut content: Buffer<'_>) -> Result<Self, Self::Error> {
-        let mut chars = content.chars();
-        let Some(c) = chars.next() else {
-            return Ok(Node(0, Value::Invalid(ParseError::new("Empty content"))));
-        };
-        let len = content.len() as u32;
-        let res = match c {
-            '"' => Node::<tokens::String>::try_parse(content).map(|n| n.map_value(Value::String)),
-            '0'..='9' => Node::<Number>::try_parse(content).map(|n| n.map_value(Value::Number)),
-            't' | 'f' => Node::<Boolean>::try_parse(content).map(|n| n.map_value(Value::Boolean)),
-            '{' => Node::<Object>::try_parse(content).map(|n| n.map_value(Value::Object)),
-            '[' => Node::<Array>::try_parse(content).map(|n| n.map_value(Value::Array)),
-            _ => {
-                return Ok(Node(len, Value::Invalid(ParseError::new("Invalid value"))));
+/// Accepts a literal of length `len` unless it runs straight into more of a word, like `nullx`.
+/// The whole word is then one malformed value.
+fn literal_end<T>(content: &str, len: usize, literal: T) -> Option<Node<T>> {
+    match content[len..].chars().next() {
+        Some(c) if is_identifier_part(c) => None,
+        _ => Some(Node(len as u32, literal)),
+    }
+}
+
+/// Returns the length of the malformed span at the start of `content`, i.e. everything up to the next
+/// `,`, `}`, `]`, newline or one of `stop` at nesting depth 0. Trailing whitespace is left out of the
+/// span so that it stays trivia of whatever token comes next. Whitespace the dialect does not allow
+/// at the start is part of the span, since it is not trivia.
+pub fn recover(content: &str, stop: &[char]) -> u32 {
+    let mut depth = 0;
+    let mut end = 0;
+    let mut chars = content.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        if depth == 0 && (c == ',' || c == '\n' || stop.contains(&c)) {
+            break;
+        }
+        match c {
+            '{' | '[' => depth += 1,
+            '}' | ']' if depth == 0 => break,
+            '}' | ']' => depth -= 1,
+            '"' => {
+                // Skip over the string so that punctuation inside it is not mistaken for a sync point.
+                while let Some(&(_, c)) = chars.peek() {
+                    if c == '\n' {
+                        break;
+                    }
+                    chars.next();
+                    if c == '"' {
+                        break;
+                    } else if c == '\\' {
+                        chars.next_if(|&(_, c)| c != '\n');
+                    }
+                }
+                end = chars.peek().map(|&(i, _)| i).unwrap_or(content.len()) as u32;
+                continue;
             }
-        };
-        Ok(res.unwrap_or_else(|e| Node(len, Value::Invalid(e))))
+            _ => {}
+        }
+        if i == 0 || !c.is_whitespace() {
+            end = (i + c.len_utf8()) as u32;
+        }
     }
+    end
 }
 
+/// Builds the tree in a single pass. Each run of trivia is scanned once, and the next byte decides
+/// what comes after it, so nothing is parsed speculatively and thrown away. Tokens are lexed once;
+/// only error recovery looks at the bytes of a malformed span again.
+///
+/// The tree has the shape of the input: the trivia in front of a pair or item belongs to it, and so
+/// does the trivia after it when there is no comma. Without a colon, the trivia after the key is all
+/// in front of where the colon would be.
 pub struct Parser<'a> {
     content: &'a str,
+    options: ParseOptions,
+    pos: u32,
+    /// How many containers enclose the value being parsed, when it is part of a larger document.
+    depth: usize,
+    /// The objects and arrays that are open, including those around a nested value.
+    objects: usize,
+    arrays: usize,
+    builder: GreenBuilder,
+    /// The tokens of the run of trivia that was scanned last, until it is known where they belong.
+    trivia: Vec<GreenToken>,
 }
 
 impl<'a> Parser<'a> {
-    pub fn new(content: &'a str) -> Self {
-        Self { content }
+    pub fn new(content: Buffer<'a>) -> Self {
+        Self {
+            content: content.content,
+            options: content.options,
+            pos: 0,
+            depth: 0,
+            objects: 0,
+            arrays: 0,
+            builder: GreenBuilder::default(),
+            trivia: Vec::new(),
+        }
+    }
+
+    /// A parser for a value that is nested in the containers of the kinds in `enclosing`. They count
+    /// towards [`ParseOptions::max_depth`], and their closing brackets end unclosed containers.
+    pub fn nested(content: Buffer<'a>, enclosing: &[SyntaxKind]) -> Self {
+        let objects = enclosing.iter().filter(|&&kind| kind == SyntaxKind::Object).count();
+        Self { depth: enclosing.len(), objects, arrays: enclosing.len() - objects, ..Self::new(content) }
+    }
+
+    fn rest(&self) -> Buffer<'a> {
+        Buffer::new(&self.content[self.pos as usize..], self.options)
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.content.as_bytes().get(self.pos as usize).copied()
+    }
+
+    fn end(&self) -> u32 {
+        self.content.len() as u32
+    }
+
+    /// Scans a run of trivia. Its tokens are added by [`Parser::flush`] once the node they lead into
+    /// is open.
+    fn scan(&mut self) {
+        self.trivia.clear();
+        while let Some((kind, len)) = trivia::next(&self.content[self.pos as usize..], self.options.json5()) {
+            self.trivia.push(GreenToken::new(kind.into(), len));
+            self.pos += len;
+        }
+    }
+
+    fn flush(&mut self) {
+        for token in self.trivia.drain(..) {
+            self.builder.token(token);
+        }
+    }
+
+    /// Scans trivia and adds it to the current node right away.
+    fn trivia(&mut self) {
+        self.scan();
+        self.flush();
+    }
+
+    /// Adds `kind` if it is the next byte.
+    fn token(&mut self, byte: u8, kind: SyntaxKind) -> bool {
+        let present = self.peek() == Some(byte);
+        if present {
+            self.pos += 1;
+            self.builder.token(GreenToken::new(kind, 1));
+        }
+        present
+    }
+
+    pub fn root(mut self) -> GreenNode {
+        self.builder.start_node(SyntaxKind::Root);
+        self.trivia();
+        self.value();
+        self.trivia();
+        if self.pos < self.end() {
+            self.builder.token(GreenToken::error(ErrorCode::TrailingContent, self.end() - self.pos));
+            self.pos = self.end();
+        }
+        self.builder.finish_node();
+        match self.builder.finish() {
+            GreenElement::Node(root) => root,
+            GreenElement::Token(_) => unreachable!("the root is a node"),
+        }
+    }
+
+    /// Parses the value at the start of the buffer, without the trivia around it.
+    pub fn parse_value(mut self) -> GreenElement {
+        self.value();
+        self.builder.finish()
+    }
+
+    /// Parses a value. Nested containers are kept on an explicit stack instead of recursing; see
+    /// [`ParseOptions::max_depth`] for what does recurse.
+    fn value(&mut self) {
+        let mut open = Vec::new();
+        loop {
+            let mut done = self.start(&mut open);
+            // Finish the member the value belongs to, and close every container that ends after it.
+            while let Some(frame) = open.last() {
+                if done {
+                    self.finish();
+                }
+                done = self.advance(frame);
+                if !done {
+                    break;
+                }
+                open.pop();
+            }
+            if done {
+                return;
+            }
+        }
+    }
+
+    /// Adds a value that is not a container and returns `true`, or opens a container.
+    fn start(&mut self, open: &mut Vec<Frame>) -> bool {
+        let frame = match self.peek() {
+            Some(b'{' | b'[') if self.depth + open.len() >= self.options.max_depth => {
+                self.too_deep();
+                return true;
+            }
+            Some(b'{') => Frame { object: true },
+            Some(b'[') => Frame { object: false },
+            // A stray closing brace is not a sync point for an array, so it becomes part of the error
+            // instead of stalling the parse.
+            Some(b'}') if matches!(open.last(), Some(Frame { object: false, .. })) => {
+                self.pos += 1;
+                self.builder.token(GreenToken::error(ErrorCode::UnexpectedToken, 1));
+                return true;
+            }
+            _ => {
+                let token = scalar(self.rest()).unwrap_or_else(|e| GreenToken::error(e.code(), recover(&self.rest(), &[])));
+                self.pos += token.width();
+                self.builder.token(token);
+                return true;
+            }
+        };
+        if frame.object {
+            self.objects += 1;
+            self.builder.start_node(SyntaxKind::Object);
+            self.token(b'{', SyntaxKind::LeftBrace);
+        } else {
+            self.arrays += 1;
+            self.builder.start_node(SyntaxKind::Array);
+            self.token(b'[', SyntaxKind::LeftBracket);
+        }
+        open.push(frame);
+        false
+    }
+
+    /// Skips a container nested deeper than the limit as a whole, up to its closing bracket or the
+    /// end of the buffer.
+    fn too_deep(&mut self) {
+        let rest = &self.content[self.pos as usize..];
+        let mut depth = 0;
+        let len = lexer::lex_with(rest, self.options)
+            .find_map(|token| {
+                match token.kind {
+                    SyntaxKind::LeftBrace | SyntaxKind::LeftBracket => depth += 1,
+                    SyntaxKind::RightBrace | SyntaxKind::RightBracket => depth -= 1,
+                    _ => {}
+                }
+                (depth == 0).then_some(token.range.end)
+            })
+            .unwrap_or(rest.len() as u32);
+        self.pos += len;
+        self.builder.token(GreenToken::error(ErrorCode::NestingTooDeep, len));
+    }
+
+    /// Adds the comma after the value of the open member, and closes the member.
+    fn finish(&mut self) {
+        self.trivia();
+        self.token(b',', SyntaxKind::Comma);
+        self.builder.finish_node();
+    }
+
+    /// Moves on to the next member of `frame`, up to where its value starts, or closes the container
+    /// and returns `true` if it ends here. A container that is not closed keeps the members parsed
+    /// so far, and ends at the end of the buffer or at the closing bracket of a container around it.
+    fn advance(&mut self, frame: &Frame) -> bool {
+        self.scan();
+        let (close, kind, other, enclosing) = match frame.object {
+            true => (b'}', SyntaxKind::RightBrace, b']', self.arrays),
+            false => (b']', SyntaxKind::RightBracket, b'}', self.objects),
+        };
+        if self.peek() == Some(close) {
+            self.flush();
+            self.token(close, kind);
+            self.close(frame);
+            return true;
+        }
+        if self.peek().is_none() || self.peek() == Some(other) && enclosing > 0 {
+            self.flush();
+            self.close(frame);
+            return true;
+        }
+        if frame.object {
+            let key = key(self.rest());
+            self.builder.start_node(SyntaxKind::Pair);
+            self.flush();
+            self.pos += key.width();
+            self.builder.token(key);
+            self.trivia();
+            self.token(b':', SyntaxKind::Colon);
+            self.trivia();
+        } else {
+            self.builder.start_node(SyntaxKind::Item);
+            self.flush();
+        }
+        false
+    }
+
+    fn close(&mut self, frame: &Frame) {
+        self.builder.finish_node();
+        if frame.object {
+            self.objects -= 1;
+        } else {
+            self.arrays -= 1;
+        }
+    }
+}
+
+/// A container whose members are being parsed.
+struct Frame {
+    object: bool,
+}
+
+/// Lexes a value that is not a container.
+fn scalar(content: Buffer<'_>) -> Result<GreenToken, ParseError> {
+    let Some(c) = content.chars().next() else {
+        return Err(ParseError::new(ErrorCode::ExpectedValue, "Empty content"));
+    };
+    let json5 = content.options.json5();
+    let number = |content| Node::<Number>::try_parse(content).map(|n| GreenToken::new(SyntaxKind::Number, n.0));
+    match c {
+        '"' => string(content),
+        '\'' if json5 => string(content),
+        '+' | '.' | 'I' | 'N' if json5 => number(content),
+        '-' | '0'..='9' => number(content),
+        't' | 'f' => Node::<Boolean>::try_parse(content).map(|n| GreenToken::new(SyntaxKind::Boolean, n.0)),
+        'n' => Node::<Null>::try_parse(content).map(|n| GreenToken::new(SyntaxKind::Null, n.0)),
+        ',' | '}' | ']' => Err(ParseError::new(ErrorCode::ExpectedValue, "Expected a value")),
+        _ => Err(ParseError::new(ErrorCode::InvalidValue, "Invalid value")),
     }
 }
 
-// pub trait Parse: Sized {
-//     fn parse(content: &mut Buffer<'_>) -> Self;
-// }
-#[derive(Debug)]
+/// Lexes a string. A malformed one keeps the code of its first problem; the diagnostics scan it
+/// again for the others.
+fn string(content: Buffer<'_>) -> Result<GreenToken, ParseError> {
+    let mut error = None;
+    match escape::scan(&content, content.options.json5(), |_, code, _| {
+        error.get_or_insert(code);
+    }) {
+        Some(len) => Ok(GreenToken::malformed(SyntaxKind::String, error, len)),
+        None if content.starts_with('\'') => Err(ParseError::new(ErrorCode::UnterminatedString, "String must end with \"'\"")),
+        None => Err(ParseError::new(ErrorCode::UnterminatedString, "String must end with '\"'")),
+    }
+}
+
+/// Lexes an object key. Anything that is not a key is an error up to the next `:` or sync point.
+fn key(content: Buffer<'_>) -> GreenToken {
+    let c = content.chars().next().expect("not at the end of the buffer");
+    let json5 = content.options.json5();
+    if c == '"' || json5 && c == '\'' {
+        if let Ok(key) = string(content.offset(0)) {
+            return key;
+        }
+    }
+    if json5 && is_identifier_start(c) {
+        let len = content.len() - content.trim_start_matches(is_identifier_part).len();
+        return GreenToken::new(SyntaxKind::Identifier, len as u32);
+    }
+    // A stray closing bracket is not a sync point for an object, so it becomes part of the error
+    // instead of stalling the parse.
+    if c == ']' {
+        return GreenToken::error(ErrorCode::UnexpectedToken, 1);
+    }
+    GreenToken::error(ErrorCode::ExpectedKey, recover(&content, &[':']))
+}
+
+/// The tree only keeps the [`ErrorCode`] of a malformed token. This recovers the error with its
+/// message by lexing the token again; `rest` is the input from where the token starts.
+pub(crate) fn error(code: ErrorCode, rest: &str, options: ParseOptions) -> ParseError {
+    let message = match code {
+        ErrorCode::UnclosedObject => "Encountered object without closing }".into(),
+        ErrorCode::UnclosedArray => "Encountered array without closing ]".into(),
+        ErrorCode::TrailingContent => "Unexpected content after the top-level value".into(),
+        ErrorCode::ExpectedKey => "Expected a string key".into(),
+        ErrorCode::UnexpectedToken => format!("Unexpected '{}'", rest.chars().next().unwrap_or_default()).into(),
+        ErrorCode::NestingTooDeep => format!("Nesting is deeper than the maximum of {}", options.max_depth).into(),
+        _ => match scalar(Buffer::new(rest, options)) {
+            Err(e) if e.code() == code => return e,
+            _ => std::borrow::Cow::Borrowed(code.as_str()),
+        },
+    };
+    ParseError::new(code, message)
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct ParseError {
+    code: ErrorCode,
     message: std::borrow::Cow<'static, str>,
 }
 
 impl ParseError {
-    pub fn new(message: impl Into<std::borrow::Cow<'static, str>>) -> Self {
+    pub fn new(code: ErrorCode, message: impl Into<std::borrow::Cow<'static, str>>) -> Self {
         Self {
+            code,
             message: message.into(),
         }
     }
-}
\ No newline at end of file
+
+    pub fn code(&self) -> ErrorCode {
+        self.code
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+/// Stable identifier for every kind of problem the parser reports. The kebab-case name returned by
+/// [`ErrorCode::as_str`] is what tools should match on; messages may change between versions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ErrorCode {
+    /// A value was expected but the input was empty or hit a separator.
+    ExpectedValue,
+    /// Content that cannot start any JSON value.
+    InvalidValue,
+    /// An object member that does not start with a string key.
+    ExpectedKey,
+    /// A specific punctuation token was expected.
+    ExpectedToken,
+    /// A closing bracket or brace that does not match the enclosing container.
+    UnexpectedToken,
+    MissingColon,
+    MissingComma,
+    TrailingComma,
+    /// A key that already appears earlier in the same object.
+    DuplicateKey,
+    /// Something other than whitespace or comments after the top-level value.
+    TrailingContent,
+    UnclosedObject,
+    UnclosedArray,
+    UnterminatedString,
+    /// A `/*` comment without `*/`.
+    UnterminatedComment,
+    /// A comment where comments are not allowed.
+    Comment,
+    /// A backslash followed by a character that does not form an escape.
+    InvalidEscape,
+    /// A `\u` escape without four hex digits.
+    InvalidUnicodeEscape,
+    /// A `\u` escape of one half of a UTF-16 surrogate pair without the other half.
+    LoneSurrogate,
+    /// A raw character below U+0020 inside a string.
+    ControlCharacter,
+    LeadingZero,
+    /// A decimal point without digits after it, as in `1.`.
+    MissingFraction,
+    /// An exponent marker without digits after it, as in `1e` or `1e+`.
+    MissingExponent,
+    InvalidNumber,
+    /// A misspelled `true`, `false` or `null`.
+    InvalidLiteral,
+    /// An object or array nested deeper than [`ParseOptions::max_depth`].
+    NestingTooDeep,
+}
+
+impl ErrorCode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ErrorCode::ExpectedValue => "expected-value",
+            ErrorCode::InvalidValue => "invalid-value",
+            ErrorCode::ExpectedKey => "expected-key",
+            ErrorCode::ExpectedToken => "expected-token",
+            ErrorCode::UnexpectedToken => "unexpected-token",
+            ErrorCode::MissingColon => "missing-colon",
+            ErrorCode::MissingComma => "missing-comma",
+            ErrorCode::TrailingComma => "trailing-comma",
+            ErrorCode::DuplicateKey => "duplicate-key",
+            ErrorCode::TrailingContent => "trailing-content",
+            ErrorCode::UnclosedObject => "unclosed-object",
+            ErrorCode::UnclosedArray => "unclosed-array",
+            ErrorCode::UnterminatedString => "unterminated-string",
+            ErrorCode::UnterminatedComment => "unterminated-comment",
+            ErrorCode::Comment => "comment",
+            ErrorCode::InvalidEscape => "invalid-escape",
+            ErrorCode::InvalidUnicodeEscape => "invalid-unicode-escape",
+            ErrorCode::LoneSurrogate => "lone-surrogate",
+            ErrorCode::ControlCharacter => "control-character",
+            ErrorCode::LeadingZero => "leading-zero",
+            ErrorCode::MissingFraction => "missing-fraction",
+            ErrorCode::MissingExponent => "missing-exponent",
+            ErrorCode::InvalidNumber => "invalid-number",
+            ErrorCode::InvalidLiteral => "invalid-literal",
+            ErrorCode::NestingTooDeep => "nesting-too-deep",
+        }
+    }
+}
+
+impl std::fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::options::{Dialect, ParseOptions};
+    use crate::SyntaxKind;
+
+    fn number(s: &str) -> Result<u32, ErrorCode> {
+        Node::<Number>::try_parse(Buffer::new(s, ParseOptions::default())).map(|n| n.0).map_err(|e| e.code())
+    }
+
+    fn json5_number(s: &str) -> Result<u32, ErrorCode> {
+        Node::<Number>::try_parse(Buffer::new(s, Dialect::Json5.into())).map(|n| n.0).map_err(|e| e.code())
+    }
+
+    #[test]
+    fn test_numbers() {
+        for s in ["0", "-0", "7", "-1", "10", "3.25", "-0.5", "1e10", "1E10", "2.5E-3", "6e+2", "-0e0"] {
+            assert_eq!(number(s), Ok(s.len() as u32), "{}", s);
+        }
+        assert_eq!(number("12, 3"), Ok(2));
+        assert_eq!(number("-4]"), Ok(2));
+    }
+
+    #[test]
+    fn test_malformed_numbers() {
+        assert_eq!(number("-"), Err(ErrorCode::InvalidNumber));
+        assert_eq!(number("-a"), Err(ErrorCode::InvalidNumber));
+        assert_eq!(number("01"), Err(ErrorCode::LeadingZero));
+        assert_eq!(number("-007"), Err(ErrorCode::LeadingZero));
+        assert_eq!(number("1."), Err(ErrorCode::MissingFraction));
+        assert_eq!(number("1.e5"), Err(ErrorCode::MissingFraction));
+        assert_eq!(number("1e"), Err(ErrorCode::MissingExponent));
+        assert_eq!(number("1e+"), Err(ErrorCode::MissingExponent));
+        assert_eq!(number("1.2.3"), Err(ErrorCode::InvalidNumber));
+        assert_eq!(number("12px"), Err(ErrorCode::InvalidNumber));
+        assert_eq!(number("1-2"), Err(ErrorCode::InvalidNumber));
+    }
+
+    #[test]
+    fn test_json5_numbers() {
+        for s in ["+1", "0x1F", "-0XaB", ".5", "5.", "+.5e2", "Infinity", "-Infinity", "NaN", "+NaN", "1e10"] {
+            assert_eq!(json5_number(s), Ok(s.len() as u32), "{}", s);
+            assert!(number(s).is_err() || s == "1e10", "{}", s);
+        }
+        assert_eq!(json5_number("0x"), Err(ErrorCode::InvalidNumber));
+        assert_eq!(json5_number("0x1G"), Err(ErrorCode::InvalidNumber));
+        assert_eq!(json5_number("."), Err(ErrorCode::MissingFraction));
+        assert_eq!(json5_number("01"), Err(ErrorCode::LeadingZero));
+        assert_eq!(json5_number("Infinityx"), Err(ErrorCode::InvalidNumber));
+        assert_eq!(json5_number("+"), Err(ErrorCode::InvalidNumber));
+    }
+
+    #[test]
+    fn test_negative_value() {
+        let s = crate::parse("[-1, -2.5e3, -]".to_string());
+        let codes: Vec<_> = s.diagnostics().iter().map(|d| (d.code, d.range.start, d.range.end)).collect();
+        assert_eq!(codes, vec![(ErrorCode::InvalidNumber, 13, 14)]);
+    }
+
+    #[test]
+    fn test_null() {
+        let s = crate::parse("{\"a\": null, \"b\": [null, nul]}".to_string());
+        let tokens = s.linearize();
+        assert_eq!(tokens[4], (SyntaxKind::Null, "null"));
+        assert_eq!(tokens[11], (SyntaxKind::Null, "null"));
+        let codes: Vec<_> = s.diagnostics().iter().map(|d| (d.code, d.range.start, d.range.end)).collect();
+        assert_eq!(codes, vec![(ErrorCode::InvalidLiteral, 24, 27)]);
+    }
+
+    #[test]
+    fn test_literal_runs_into_word() {
+        let s = crate::parse("[nullx, trueish, false_, true]".to_string());
+        let codes: Vec<_> = s.diagnostics().iter().map(|d| (d.code, d.range.start, d.range.end)).collect();
+        assert_eq!(codes, vec![
+            (ErrorCode::InvalidLiteral, 1, 6),
+            (ErrorCode::InvalidLiteral, 8, 15),
+            (ErrorCode::InvalidLiteral, 17, 23),
+        ]);
+        assert_eq!(s.linearize()[10], (SyntaxKind::Boolean, "true"));
+    }
+
+    #[test]
+    fn test_max_depth() {
+        let options = ParseOptions { max_depth: 2, ..ParseOptions::default() };
+        let codes = |text: &str| {
+            let s = crate::parse_with(text.to_string(), options);
+            assert_eq!(s.to_string(), text);
+            s.diagnostics().iter().map(|d| (d.code, d.range.start, d.range.end)).collect::<Vec<_>>()
+        };
+        assert_eq!(codes("[[1], {\"a\": 2}]"), vec![]);
+        // The container that is too deep is skipped as a whole, brackets in strings and comments included.
+        assert_eq!(codes("[[[\"]\" /* ] */]], {\"a\": {\"b\": 1}}, 2]"), vec![
+            (ErrorCode::NestingTooDeep, 2, 15),
+            (ErrorCode::NestingTooDeep, 24, 32),
+        ]);
+        // An unclosed container keeps its members, as it does at any depth.
+        assert_eq!(codes("[[[1], 2]"), vec![(ErrorCode::UnclosedArray, 0, 1), (ErrorCode::NestingTooDeep, 2, 5)]);
+        let s = crate::parse(format!("{}{}", "[".repeat(200), "]".repeat(200)));
+        let codes: Vec<_> = s.diagnostics().iter().map(|d| (d.code, d.range.start, d.range.end)).collect();
+        assert_eq!(codes, vec![(ErrorCode::NestingTooDeep, 128, 272)]);
+    }
+
+    /// The parser keeps open containers on the heap, so no depth overflows the stack.
+    #[test]
+    fn test_stack_safe() {
+        let options = ParseOptions { max_depth: usize::MAX, ..ParseOptions::default() };
+        let s = crate::parse_with("[{\"a\":\n".repeat(100_000), options);
+        let codes: Vec<_> = s.diagnostics().iter().map(|d| d.code).collect();
+        assert_eq!(codes.len(), 200_001);
+        assert_eq!(&codes[..2], [ErrorCode::UnclosedArray, ErrorCode::UnclosedObject]);
+        assert_eq!(codes.last(), Some(&ErrorCode::ExpectedValue));
+        let s = crate::parse("[".repeat(100_000));
+        let codes: Vec<_> = s.diagnostics().iter().map(|d| d.code).collect();
+        assert_eq!(codes.len(), 129);
+        assert_eq!(codes.last(), Some(&ErrorCode::NestingTooDeep));
+
+        // A well-formed document far deeper than the call stack allows, end to end.
+        let text = format!("{}[1,]{}", "[{\"a\": ".repeat(30_000), "}]".repeat(30_000));
+        let s = crate::parse_with(text.clone(), options);
+        assert!(!s.tree().value.1 .1.has_error());
+        assert_eq!(s.to_string(), text);
+        let diagnostics = s.diagnostics();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].code, ErrorCode::TrailingComma);
+        assert!(!s.has_error());
+        drop(s);
+    }
+}
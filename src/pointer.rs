@@ -0,0 +1,127 @@
+use crate::ast::{Array, Object, Pair};
+use crate::syntax::{SyntaxKind, SyntaxNode};
+
+/// Splits an RFC 6901 pointer into its unescaped reference tokens. Returns `None` if the pointer is
+/// neither empty nor starts with `/`.
+pub(crate) fn tokens(pointer: &str) -> Option<Vec<String>> {
+    if pointer.is_empty() {
+        return Some(Vec::new());
+    }
+    let rest = pointer.strip_prefix('/')?;
+    Some(rest.split('/').map(|t| t.replace("~1", "/").replace("~0", "~")).collect())
+}
+
+/// Resolves `pointer` against the value below `root`.
+pub(crate) fn resolve<'a>(root: SyntaxNode<'a>, pointer: &str) -> Option<SyntaxNode<'a>> {
+    walk(root, &tokens(pointer)?)
+}
+
+pub(crate) fn walk<'a>(root: SyntaxNode<'a>, tokens: &[String]) -> Option<SyntaxNode<'a>> {
+    let mut node = root.first_child()?;
+    for token in tokens {
+        node = step(&node, token)?;
+    }
+    Some(node)
+}
+
+/// Moves from a container to the child value named by `token`.
+pub(crate) fn step<'a>(node: &SyntaxNode<'a>, token: &str) -> Option<SyntaxNode<'a>> {
+    if let Some(object) = Object::cast(node.clone()) {
+        object.get(token)
+    } else if let Some(array) = Array::cast(node.clone()) {
+        array.get(index(token)?)
+    } else {
+        None
+    }
+}
+
+/// Array indices may not have leading zeros.
+pub(crate) fn index(token: &str) -> Option<usize> {
+    if token.is_empty() || (token.len() > 1 && token.starts_with('0')) || !token.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    token.parse().ok()
+}
+
+/// The pointer of the innermost value at `offset`. On a key, that is the value of its pair; outside
+/// the top-level value, it is the empty pointer.
+pub(crate) fn at(root: SyntaxNode<'_>, offset: u32) -> String {
+    let mut pointer = String::new();
+    let Some(mut node) = root.first_child() else {
+        return pointer;
+    };
+    while let Some((i, child)) = child_at(&node, offset) {
+        let (token, value) = match child.kind() {
+            SyntaxKind::Pair => {
+                let pair = Pair::cast(child).expect("kind is checked");
+                match (pair.key_text(), pair.value()) {
+                    (Some(key), Some(value)) => (key.into_owned(), value),
+                    _ => break,
+                }
+            }
+            SyntaxKind::Item => match child.first_child() {
+                Some(value) => (i.to_string(), value),
+                None => break,
+            },
+            _ => break,
+        };
+        pointer.push('/');
+        pointer.push_str(&token.replace('~', "~0").replace('/', "~1"));
+        node = value;
+    }
+    pointer
+}
+
+/// The child of `node` that contains `offset`, or that ends right at it, with its index.
+fn child_at<'a>(node: &SyntaxNode<'a>, offset: u32) -> Option<(usize, SyntaxNode<'a>)> {
+    let mut touching = None;
+    for (i, child) in node.children().enumerate() {
+        let range = child.text_range();
+        if range.start <= offset && offset < range.end {
+            return Some((i, child));
+        }
+        if range.end == offset {
+            touching = Some((i, child));
+        }
+    }
+    touching
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parse;
+
+    #[test]
+    fn test_pointer() {
+        let source = parse(include_str!("../tests/data/multi.json").to_string());
+        assert_eq!(source.pointer("").unwrap().text(), source.content.trim_end());
+        assert_eq!(source.pointer("/d/3/e").unwrap().text(), "\"f\"");
+        assert_eq!(source.pointer("/d/4").unwrap().text(), "3.0");
+        assert_eq!(source.pointer("/e/f").unwrap().text_range().start, source.content.find("\"g\"").unwrap() as u32);
+        for missing in ["/x", "/d/5", "/d/01", "/b/0", "d"] {
+            assert_eq!(source.pointer(missing), None, "{}", missing);
+        }
+        let source = parse("{\"a/b\": {\"m~n\": 1}}".to_string());
+        assert_eq!(source.pointer("/a~1b/m~0n").unwrap().text(), "1");
+    }
+
+    #[test]
+    fn test_pointer_at() {
+        let text = include_str!("../tests/data/multi.json");
+        let source = parse(text.to_string());
+        let at = |needle: &str| text.find(needle).unwrap() as u32;
+        assert_eq!(source.pointer_at(at("\"f\" }")), "/d/3/e");
+        assert_eq!(source.pointer_at(at("\"two\"") + 1), "/d/1");
+        assert_eq!(source.pointer_at(at(", \"two\"")), "/d/0");
+        assert_eq!(source.pointer_at(at("\"e\": {")), "/e");
+        assert_eq!(source.pointer_at(at("{ \"e\"")), "/d/3");
+        assert_eq!(source.pointer_at(0), "");
+        assert_eq!(source.pointer_at(text.len() as u32), "");
+        for needle in ["\"x\"", "1,", "true,", "3.0", "\"g\""] {
+            let pointer = source.pointer_at(at(needle));
+            assert_eq!(source.pointer(&pointer).unwrap().text_range().start, at(needle), "{}", pointer);
+        }
+        let source = parse("{\"a/b\": {\"m~n\": [1]}}".to_string());
+        assert_eq!(source.pointer_at(17), "/a~1b/m~0n/0");
+    }
+}
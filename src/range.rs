@@ -0,0 +1,43 @@
+/// A half-open byte range `start..end` into the source text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct TextRange {
+    pub start: u32,
+    pub end: u32,
+}
+
+impl TextRange {
+    pub fn new(start: u32, end: u32) -> Self {
+        debug_assert!(start <= end);
+        Self { start, end }
+    }
+
+    pub fn at(offset: u32, len: u32) -> Self {
+        Self::new(offset, offset + len)
+    }
+
+    pub fn empty(offset: u32) -> Self {
+        Self::new(offset, offset)
+    }
+
+    pub fn len(&self) -> u32 {
+        self.end - self.start
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+
+    pub fn contains(&self, offset: u32) -> bool {
+        self.start <= offset && offset < self.end
+    }
+
+    pub fn contains_range(&self, other: TextRange) -> bool {
+        self.start <= other.start && other.end <= self.end
+    }
+}
+
+impl From<TextRange> for std::ops::Range<usize> {
+    fn from(r: TextRange) -> Self {
+        r.start as usize..r.end as usize
+    }
+}
@@ -1,20 +1,22 @@
-use crate::{Linearize, LinearizeBuffer};
-use crate::tokens::Node;
-use crate::tokens::Offset;
-use crate::tokens::Value;
+use std::fmt;
+use crate::{diagnostic, pointer, Diagnostic, Linearize, LinearizeBuffer, ParseOptions, SyntaxKind, SyntaxNode, SyntaxToken, TextRange};
+use crate::green::GreenNode;
+use crate::tokens::Root;
 
 /// Rules
 /// 1. The parent is responsible for "cleaning up" the surroundings. i.e. having offset<node<_>>
 /// 2. Every child lives inside Offset
 /// 3. If a child is variable length, it lives inside Node.
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Source {
     pub content: String,
-    pub dom: Offset<Node<Value>>,
+    pub dom: GreenNode,
+    /// The options the content was parsed with, which reparsing after an edit reuses.
+    pub options: ParseOptions,
 }
 
 impl Source {
-    pub fn linearize(&self) -> LinearizeBuffer {
+    pub fn linearize(&self) -> LinearizeBuffer<'_> {
         let mut result = Vec::new();
         self.dom.linearize(self.content.as_str(), &mut result);
         result
@@ -26,7 +28,194 @@ impl Source {
         result.into_iter().map(|(_, token)| token).collect()
     }
 
+    /// Every token with its kind and absolute range, in document order. Together the tokens cover
+    /// the whole input, trivia and malformed parts included.
+    pub fn tokens(&self) -> impl Iterator<Item = (SyntaxKind, TextRange, &str)> {
+        let mut offset = 0;
+        self.linearize().into_iter().map(move |(kind, text)| {
+            let range = TextRange::at(offset, text.len() as u32);
+            offset = range.end;
+            (kind, range, text)
+        })
+    }
+
+    /// Whether the document failed to parse or breaks a policy that is set to [`Policy::Error`].
     pub fn has_error(&self) -> bool {
-        self.dom.1 .1.has_error()
+        diagnostic::has_error(&self.content, &self.dom, self.options)
+    }
+
+    /// The typed view of the tree.
+    pub(crate) fn tree(&self) -> Root<'_> {
+        Root::new(&self.dom)
+    }
+
+    /// The root of the navigation tree, spanning the whole document.
+    pub fn root(&self) -> SyntaxNode<'_> {
+        SyntaxNode::root(self)
+    }
+
+    /// The innermost node whose range contains `offset`; its [`SyntaxNode::ancestors`] lead back up
+    /// to the root. Trivia between members belongs to the enclosing container, and an offset at the
+    /// end of the document gives the root.
+    pub fn node_at(&self, offset: u32) -> SyntaxNode<'_> {
+        self.root().node_at(offset)
+    }
+
+    /// The token that contains `offset`, including whitespace and comments, or `None` at the end of
+    /// the document.
+    pub fn token_at(&self, offset: u32) -> Option<SyntaxToken<'_>> {
+        self.root().token_at(offset)
+    }
+
+    /// The value that the RFC 6901 `pointer` names, e.g. `/d/3/e`, or `None` if the pointer is
+    /// malformed or names nothing.
+    pub fn pointer(&self, pointer: &str) -> Option<SyntaxNode<'_>> {
+        pointer::resolve(self.root(), pointer)
+    }
+
+    /// The pointer of the value at `offset`, the inverse of [`Source::pointer`]. On a key, this is
+    /// the pointer of the pair's value.
+    pub fn pointer_at(&self, offset: u32) -> String {
+        pointer::at(self.root(), offset)
+    }
+
+    pub fn diagnostics(&self) -> Vec<Diagnostic> {
+        diagnostic::collect(self)
+    }
+
+    /// Writes every token and every piece of trivia in the tree, in order. The tree covers the whole
+    /// input, so this reproduces `content` byte for byte, malformed parts included.
+    pub fn write_to(&self, w: &mut impl fmt::Write) -> fmt::Result {
+        for (_, token) in self.linearize() {
+            w.write_str(token)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write_to(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{parse, parse_with, Dialect, SyntaxKind, TextRange};
+    use crate::test_support::Rng;
+
+    struct Generator(Rng);
+
+    impl Generator {
+        fn next(&mut self, n: usize) -> usize {
+            self.0.next(n)
+        }
+
+        fn pick<'a>(&mut self, items: &[&'a str]) -> &'a str {
+            self.0.pick(items)
+        }
+
+        fn trivia(&mut self, out: &mut String) {
+            for _ in 0..self.next(3) {
+                out.push_str(self.pick(&[" ", "  ", "\n", "\t", "\r\n", "// line\n", "/* block */", "/**/"]));
+            }
+        }
+
+        fn value(&mut self, depth: usize, out: &mut String) {
+            let kind = if depth == 0 { self.next(4) } else { self.next(6) };
+            match kind {
+                0 => out.push_str(self.pick(&["0", "-1", "3.25", "1e10", "-0.5E-3", "12"])),
+                1 => out.push_str(self.pick(&["\"\"", "\"a\"", "\"\\n\\u00e9\"", "\"😀 é\"", "\"\\\"q\\\"\""])),
+                2 => out.push_str(self.pick(&["true", "false"])),
+                3 => out.push_str("null"),
+                4 => self.container(out, '[', ']', |g, out| g.value(depth - 1, out)),
+                _ => self.container(out, '{', '}', |g, out| {
+                    out.push_str(g.pick(&["\"k\"", "\"key\"", "\"\\u0061\"", "\"\""]));
+                    g.trivia(out);
+                    out.push(':');
+                    g.trivia(out);
+                    g.value(depth - 1, out);
+                }),
+            }
+        }
+
+        fn container(&mut self, out: &mut String, open: char, close: char, mut member: impl FnMut(&mut Self, &mut String)) {
+            out.push(open);
+            let len = self.next(4);
+            for i in 0..len {
+                self.trivia(out);
+                member(self, out);
+                self.trivia(out);
+                if i + 1 < len || self.next(3) == 0 {
+                    out.push(',');
+                }
+            }
+            self.trivia(out);
+            out.push(close);
+        }
+
+        /// Deletes a few bytes and inserts a snippet somewhere, which mostly produces broken input.
+        fn mutate(&mut self, text: &mut String) {
+            let range = self.0.range(text);
+            let insert = self.pick(&["", "{", "}", "[", "]", ",", ":", "\"", "'", "/", "/*", "\\", "x", "-", ".", "é", "\n"]);
+            text.replace_range(range, insert);
+        }
+    }
+
+    #[test]
+    fn test_display() {
+        let text = "// c\n{\"a\": [1,], \"b\": tru} trailing\n";
+        let source = parse(text.to_string());
+        assert_eq!(source.to_string(), text);
+        let mut out = String::new();
+        source.write_to(&mut out).unwrap();
+        assert_eq!(out, text);
+    }
+
+    #[test]
+    fn test_tokens() {
+        use SyntaxKind::*;
+        let source = parse("{\"a\": [1, tru]} // c\n".to_string());
+        let tokens: Vec<_> = source.tokens().map(|(kind, range, text)| (kind, range.start, text)).collect();
+        assert_eq!(tokens, vec![
+            (LeftBrace, 0, "{"),
+            (String, 1, "\"a\""),
+            (Colon, 4, ":"),
+            (Whitespace, 5, " "),
+            (LeftBracket, 6, "["),
+            (Number, 7, "1"),
+            (Comma, 8, ","),
+            (Whitespace, 9, " "),
+            (Error, 10, "tru"),
+            (RightBracket, 13, "]"),
+            (RightBrace, 14, "}"),
+            (Whitespace, 15, " "),
+            (LineComment, 16, "// c\n"),
+        ]);
+        // The tokens agree with the ones found by offset.
+        for (kind, range, _) in source.tokens() {
+            let token = source.token_at(range.start).unwrap();
+            assert_eq!((token.kind(), token.text_range()), (kind, range));
+        }
+        assert!(source.tokens().all(|(_, range, text)| range.len() as usize == text.len() && range != TextRange::empty(0)));
+    }
+
+    /// Every input round-trips, whether it is well-formed or not.
+    #[test]
+    fn test_round_trip() {
+        let mut g = Generator(Rng::new(0x9e37_79b9_7f4a_7c15));
+        for _ in 0..2000 {
+            let mut text = String::new();
+            g.trivia(&mut text);
+            g.value(3, &mut text);
+            g.trivia(&mut text);
+            assert_eq!(parse(text.clone()).to_string(), text);
+            for _ in 0..g.next(4) {
+                g.mutate(&mut text);
+            }
+            for dialect in [Dialect::Json, Dialect::Jsonc, Dialect::Json5] {
+                assert_eq!(parse_with(text.clone(), dialect.into()).to_string(), text, "{:?}", dialect);
+            }
+        }
     }
 }
\ No newline at end of file
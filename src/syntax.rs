@@ -0,0 +1,346 @@
+use std::fmt::Debug;
+use std::rc::Rc;
+use crate::options::ParseOptions;
+use crate::range::TextRange;
+use crate::source::Source;
+use crate::green::{GreenElement, GreenNode, GreenToken};
+use crate::tokens::Trivia;
+use crate::width::Width;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SyntaxKind {
+    /// The whole document, including trivia around the top-level value.
+    Root,
+    Object,
+    Array,
+    /// A key, colon, value and optional trailing comma inside an object.
+    Pair,
+    /// A value and optional trailing comma inside an array.
+    Item,
+    String,
+    Number,
+    Boolean,
+    Null,
+    /// An unquoted JSON5 key.
+    Identifier,
+    Error,
+    LeftBrace,
+    RightBrace,
+    LeftBracket,
+    RightBracket,
+    Colon,
+    Comma,
+    Whitespace,
+    /// A `//` comment, including the newline that ends it.
+    LineComment,
+    BlockComment,
+}
+
+impl SyntaxKind {
+    pub fn is_trivia(self) -> bool {
+        matches!(self, SyntaxKind::Whitespace | SyntaxKind::LineComment | SyntaxKind::BlockComment)
+    }
+
+    fn is_punctuation(self) -> bool {
+        matches!(
+            self,
+            SyntaxKind::LeftBrace | SyntaxKind::RightBrace | SyntaxKind::LeftBracket | SyntaxKind::RightBracket | SyntaxKind::Colon | SyntaxKind::Comma
+        )
+    }
+}
+
+impl From<Trivia> for SyntaxKind {
+    fn from(trivia: Trivia) -> Self {
+        match trivia {
+            Trivia::Whitespace => SyntaxKind::Whitespace,
+            Trivia::LineComment => SyntaxKind::LineComment,
+            Trivia::BlockComment => SyntaxKind::BlockComment,
+        }
+    }
+}
+
+/// A node of the lossless tree with its absolute position and a link to its parent.
+///
+/// The underlying tree only stores relative widths; a `SyntaxNode` is created on the way down and
+/// remembers its absolute range, so that navigating never has to re-sum widths from the root.
+/// Cloning is cheap.
+#[derive(Clone)]
+pub struct SyntaxNode<'a>(Rc<NodeData<'a>>);
+
+struct NodeData<'a> {
+    source: &'a str,
+    options: ParseOptions,
+    element: Element<'a>,
+    /// Where the green element starts, which is before the range for a pair or item with leading
+    /// trivia.
+    offset: u32,
+    range: TextRange,
+    parent: Option<SyntaxNode<'a>>,
+}
+
+#[derive(Clone, Copy)]
+enum Element<'a> {
+    Node(&'a GreenNode),
+    Token(&'a GreenToken),
+}
+
+impl<'a> SyntaxNode<'a> {
+    pub(crate) fn root(source: &'a Source) -> Self {
+        SyntaxNode(Rc::new(NodeData {
+            source: &source.content,
+            options: source.options,
+            element: Element::Node(&source.dom),
+            offset: 0,
+            range: TextRange::new(0, source.content.len() as u32),
+            parent: None,
+        }))
+    }
+
+    fn child(&self, element: &'a GreenElement, offset: u32) -> Self {
+        let (element, range) = match element {
+            GreenElement::Node(node) => (Element::Node(node), trim(node, offset)),
+            GreenElement::Token(token) => (Element::Token(token), TextRange::at(offset, token.width())),
+        };
+        SyntaxNode(Rc::new(NodeData {
+            source: self.0.source,
+            options: self.0.options,
+            element,
+            offset,
+            range,
+            parent: Some(self.clone()),
+        }))
+    }
+
+    pub fn kind(&self) -> SyntaxKind {
+        match self.0.element {
+            Element::Node(node) => node.kind(),
+            Element::Token(token) => token.kind(),
+        }
+    }
+
+    /// The range of this node. Pairs and items span from their first token to their comma, or to the
+    /// end of their value if there is no comma; leading trivia belongs to the parent.
+    pub fn text_range(&self) -> TextRange {
+        self.0.range
+    }
+
+    pub fn text(&self) -> &'a str {
+        &self.0.source[std::ops::Range::from(self.0.range)]
+    }
+
+    pub(crate) fn options(&self) -> ParseOptions {
+        self.0.options
+    }
+
+    pub fn parent(&self) -> Option<SyntaxNode<'a>> {
+        self.0.parent.clone()
+    }
+
+    pub fn ancestors(&self) -> impl Iterator<Item = SyntaxNode<'a>> {
+        std::iter::successors(Some(self.clone()), |n| n.parent())
+    }
+
+    /// The nodes below this one: the value of the document and any trailing content, the members
+    /// of a container, and the key and value of a pair. Trivia and punctuation are only tokens.
+    pub fn children(&self) -> std::vec::IntoIter<SyntaxNode<'a>> {
+        let mut children = Vec::new();
+        if let Element::Node(node) = self.0.element {
+            let mut offset = self.0.offset;
+            for child in node.children() {
+                if !child.kind().is_trivia() && !child.kind().is_punctuation() {
+                    children.push(self.child(child, offset));
+                }
+                offset += child.width();
+            }
+        }
+        children.into_iter()
+    }
+
+    /// Whether this node or anything below it failed to parse.
+    pub fn has_error(&self) -> bool {
+        match self.0.element {
+            Element::Node(node) => node.has_error(),
+            Element::Token(token) => token.code().is_some(),
+        }
+    }
+
+    pub fn first_child(&self) -> Option<SyntaxNode<'a>> {
+        self.children().next()
+    }
+
+    /// The innermost node below this one whose range contains `offset`, or this node if no child
+    /// does. Each step finds the child by the widths in the tree, and only creates that one.
+    pub(crate) fn node_at(&self, offset: u32) -> SyntaxNode<'a> {
+        let mut node = self.clone();
+        while let Some(child) = node.child_at(offset) {
+            node = child;
+        }
+        node
+    }
+
+    /// The child whose range contains `offset`, if there is one.
+    fn child_at(&self, offset: u32) -> Option<SyntaxNode<'a>> {
+        let Element::Node(node) = self.0.element else {
+            return None;
+        };
+        let (child, start) = element_at(node, self.0.offset, offset)?;
+        if child.kind().is_trivia() || child.kind().is_punctuation() {
+            return None;
+        }
+        let range = match child {
+            GreenElement::Node(node) => trim(node, start),
+            GreenElement::Token(token) => TextRange::at(start, token.width()),
+        };
+        (range.start <= offset && offset < range.end).then(|| self.child(child, start))
+    }
+
+    /// The token that contains `offset`, trivia included. Below the innermost node, the token is
+    /// found by the widths in the tree, through the members whose edge trivia it is in.
+    pub(crate) fn token_at(&self, offset: u32) -> Option<SyntaxToken<'a>> {
+        if !(self.0.range.start <= offset && offset < self.0.range.end) {
+            return None;
+        }
+        let node = self.node_at(offset);
+        let Element::Node(mut green) = node.0.element else {
+            return Some(SyntaxToken { kind: node.kind(), range: node.0.range, parent: node });
+        };
+        let mut start = node.0.offset;
+        loop {
+            match element_at(green, start, offset)? {
+                (GreenElement::Node(child), child_start) => {
+                    green = child;
+                    start = child_start;
+                }
+                (GreenElement::Token(token), start) => {
+                    let range = TextRange::at(start, token.width());
+                    return Some(SyntaxToken { kind: token.kind(), range, parent: node });
+                }
+            }
+        }
+    }
+}
+
+/// The child of `node`, which starts at `start`, that contains `offset`, and where it starts.
+fn element_at(node: &GreenNode, mut start: u32, offset: u32) -> Option<(&GreenElement, u32)> {
+    for child in node.children() {
+        let end = start + child.width();
+        if offset < end {
+            return (start <= offset).then_some((child, start));
+        }
+        start = end;
+    }
+    None
+}
+
+/// The range of a node at `offset`, without the trivia at its edges. Only pairs and items have any.
+fn trim(node: &GreenNode, offset: u32) -> TextRange {
+    let trivia = |children: &mut dyn Iterator<Item = &GreenElement>| -> u32 {
+        children.take_while(|c| c.kind().is_trivia()).map(Width::width).sum()
+    };
+    let start = offset + trivia(&mut node.children().iter());
+    let end = offset + node.width() - trivia(&mut node.children().iter().rev());
+    TextRange::new(start, end)
+}
+
+/// A single token with its absolute range. `parent` is the innermost node containing it; for a
+/// string, number, literal or error that is the node of the same kind spanning exactly the token.
+#[derive(Clone, PartialEq)]
+pub struct SyntaxToken<'a> {
+    kind: SyntaxKind,
+    range: TextRange,
+    parent: SyntaxNode<'a>,
+}
+
+impl<'a> SyntaxToken<'a> {
+    pub fn kind(&self) -> SyntaxKind {
+        self.kind
+    }
+
+    pub fn text_range(&self) -> TextRange {
+        self.range
+    }
+
+    pub fn text(&self) -> &'a str {
+        &self.parent.0.source[std::ops::Range::from(self.range)]
+    }
+
+    pub fn parent(&self) -> SyntaxNode<'a> {
+        self.parent.clone()
+    }
+
+    /// The nodes containing this token, from the innermost one up to the document.
+    pub fn ancestors(&self) -> impl Iterator<Item = SyntaxNode<'a>> {
+        self.parent.ancestors()
+    }
+}
+
+impl Debug for SyntaxToken<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}@{}..{}", self.kind, self.range.start, self.range.end)
+    }
+}
+
+impl PartialEq for SyntaxNode<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.kind() == other.kind() && self.0.range == other.0.range
+    }
+}
+
+impl Debug for SyntaxNode<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}@{}..{}", self.kind(), self.0.range.start, self.0.range.end)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+
+    #[test]
+    fn test_node_at() {
+        let text = "{\"a\": [1, {\"b\": null}], \"c\" : tru}";
+        let source = parse(text.to_string());
+        let at = |needle: &str| text.find(needle).unwrap() as u32;
+        let kinds = |node: SyntaxNode<'_>| node.ancestors().map(|n| n.kind()).collect::<Vec<_>>();
+        use SyntaxKind::*;
+        assert_eq!(kinds(source.node_at(at("null"))), vec![Null, Pair, Object, Item, Array, Pair, Object, Root]);
+        assert_eq!(source.node_at(at("null") + 4).kind(), Object);
+        assert_eq!(kinds(source.node_at(at("tru"))), vec![Error, Pair, Object, Root]);
+        assert_eq!(source.node_at(at(" :")).kind(), Pair);
+        assert_eq!(source.node_at(text.len() as u32).kind(), Root);
+    }
+
+    #[test]
+    fn test_token_at() {
+        let text = "// c\n{\"a\" : [1, /* x */ 2],}";
+        let source = parse(text.to_string());
+        let at = |needle: &str| text.find(needle).unwrap() as u32;
+        let token = |offset: u32| {
+            let token = source.token_at(offset).unwrap();
+            (token.kind(), token.text(), token.parent().kind())
+        };
+        use SyntaxKind::*;
+        assert_eq!(token(0), (LineComment, "// c\n", Root));
+        assert_eq!(token(at("{")), (LeftBrace, "{", Object));
+        assert_eq!(token(at("\"a\"") + 1), (String, "\"a\"", String));
+        assert_eq!(token(at(" :")), (Whitespace, " ", Pair));
+        assert_eq!(token(at(":")), (Colon, ":", Pair));
+        assert_eq!(token(at("[")), (LeftBracket, "[", Array));
+        assert_eq!(token(at("1")), (Number, "1", Number));
+        assert_eq!(token(at(",")), (Comma, ",", Item));
+        assert_eq!(token(at("x")), (BlockComment, "/* x */", Array));
+        assert_eq!(token(at("]")), (RightBracket, "]", Array));
+        assert_eq!(token(at("],") + 1), (Comma, ",", Pair));
+        assert_eq!(token(at("}")), (RightBrace, "}", Object));
+        assert_eq!(source.token_at(text.len() as u32), None);
+
+        // Every byte is covered by exactly the token that the tokens around it leave off at.
+        let mut offset = 0;
+        while offset < text.len() as u32 {
+            let token = source.token_at(offset).unwrap();
+            assert_eq!(token.text_range().start, offset);
+            offset = token.text_range().end;
+        }
+    }
+}
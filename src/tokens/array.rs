@@ -1,84 +1,92 @@
-use crate::{Linearize, LinearizeBuffer};
-use crate::buffer::Buffer;
-use crate::parse::{ParseError, TryParse};
+use crate::green::{GreenElement, GreenNode};
+use crate::syntax::SyntaxKind;
 use crate::tokens::item::Item;
+use crate::tokens::object::closing_trivia;
 use crate::tokens::{LeftBracket, RightBracket};
-use crate::tokens::node::Node;
 use crate::tokens::offset::Offset;
-use crate::width::Width;
 
-#[derive(Debug)]
-pub struct Array {
-    pub left_bracket: Offset<LeftBracket>,
-    pub values: Vec<Item>,
-    pub right_bracket: Offset<RightBracket>,
-}
+/// An array in the green tree. Like an [`Object`](crate::tokens::Object), it has no `]` if it is
+/// unclosed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Array<'g>(pub &'g GreenNode);
 
-impl Linearize for Array {
-    fn linearize<'a>(&'a self, source: &'a str, buf: &mut LinearizeBuffer<'a>) {
-        self.left_bracket.linearize(source, buf);
-        for v in &self.values {
-            v.linearize(source, buf);
-        }
-        self.right_bracket.linearize(source, buf);
+impl<'g> Array<'g> {
+    pub fn left_bracket(&self) -> Offset<LeftBracket> {
+        Offset(0, LeftBracket)
+    }
+
+    /// The members, decoded from the tree each time they are asked for.
+    pub fn values(&self) -> Vec<Item<'g>> {
+        self.0.children().iter().filter_map(GreenElement::as_node).map(Item::new).collect()
     }
-}
 
-impl TryParse for Node<Array> {
-    type Error = ParseError;
+    /// The `]`, after the trivia that does not belong to the last item.
+    pub fn right_bracket(&self) -> Offset<Option<RightBracket>> {
+        let closed = self.0.children().last().map(GreenElement::kind) == Some(SyntaxKind::RightBracket);
+        Offset(closing_trivia(self.0), closed.then_some(RightBracket))
+    }
 
-    fn try_parse(mut content: Buffer<'_>) -> Result<Self, Self::Error> {
-        let left_bracket = Offset::<LeftBracket>::try_parse(content.offset(0))?;
-        let mut values: Vec<Item> = Vec::new();
-        let mut offset = left_bracket.width();
-        let right_bracket = loop {
-            if (content.len() as u32) <= offset {
-                return Err(ParseError::new("Encountered array without closing ["));
-            }
-            if let Ok(right_bracket) = Offset::<RightBracket>::try_parse(content.offset(offset)) {
-                offset += right_bracket.width();
-                break right_bracket;
-            }
-            let v = Item::try_parse(content.offset(offset))?;
-            if let Some(last) = values.last() {
-                if last.comma.is_none() {
-                    return Err(ParseError::new("Encountered array items not separated by comma"));
-                }
-            }
-            offset += v.width();
-            // let mut buf = Vec::new();
-            // v.linearize(&content.content[(offset - v.width()) as usize..offset as usize], &mut buf);
-            // dbg!("parsed item", buf, offset);
-            values.push(v);
-        };
-        let array = Array {
-            left_bracket,
-            values,
-            right_bracket,
-        };
-        // content.slice_start(len);
-        Ok(Node(offset, array))
+    pub fn has_error(&self) -> bool {
+        self.0.has_error()
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::*;
+    use crate::parse::ErrorCode;
+    use crate::tokens::Value;
+    use crate::width::Width;
 
     #[test]
     fn test_array() {
-        let s = "[1, 2, 3]";
-        let s = Buffer { content: s };
-        let s = Node::<Array>::try_parse(s).unwrap();
-        let mut buf = Vec::new();
-        s.linearize("[1, 2, 3]", &mut buf);
+        let s = crate::parse("[1, 2, 3]".to_string());
+        assert_eq!(s.linearize().len(), 9);
     }
 
     #[test]
     fn test_commas_between() {
-        let s = "[1 2,]";
-        let s = Buffer { content: s };
-        let s = Node::<Array>::try_parse(s);
-        assert!(s.is_err());
+        let s = crate::parse("[1 2,]".to_string());
+        let Value::Array(a) = s.tree().value.1 .1 else { panic!() };
+        let values = a.values();
+        assert_eq!(values.len(), 2);
+        assert!(values[0].comma.1.is_none());
+        assert!(Value::Array(a).has_error());
+    }
+
+    #[test]
+    fn test_invalid_item_stops_at_comma() {
+        let s = crate::parse("[1, nope, 3]".to_string());
+        let Value::Array(a) = s.tree().value.1 .1 else { panic!() };
+        let values = a.values();
+        assert_eq!(values.len(), 3);
+        let item = &values[1].item;
+        assert_eq!((item.0, item.1 .0), (1, 4));
+        assert!(matches!(item.1 .1, Value::Invalid(_)));
+        assert!(matches!(values[2].item.1 .1, Value::Number(_)));
+    }
+
+    #[test]
+    fn test_stray_brace() {
+        let s = crate::parse("[1}, 2]".to_string());
+        let Value::Array(a) = s.tree().value.1 .1 else { panic!() };
+        assert_eq!(a.values().len(), 3);
+        assert_eq!(a.0.width(), 7);
+    }
+
+    #[test]
+    fn test_unterminated() {
+        let s = crate::parse("[1, 2".to_string());
+        let Value::Array(a) = s.tree().value.1 .1 else { panic!() };
+        assert_eq!(a.values().len(), 2);
+        assert!(a.right_bracket().1.is_none());
+        assert_eq!(s.diagnostics()[0].code, ErrorCode::UnclosedArray);
+
+        // A closing brace of the object around it ends the array, and the members survive.
+        let s = crate::parse("{\"a\": [1, 2, \"b\": 3}".to_string());
+        let Value::Object(o) = s.tree().value.1 .1 else { panic!() };
+        assert!(o.right_brace().1.is_some());
+        let Value::Array(a) = o.pairs()[0].value.1 .1 else { panic!() };
+        assert_eq!(a.values().len(), 4);
+        assert_eq!(a.0.width(), 13);
     }
 }
@@ -1,38 +1,28 @@
-use crate::{Linearize, LinearizeBuffer};
-use crate::buffer::Buffer;
-use crate::parse::{ParseError, TryParse};
-use crate::tokens::Comma;
+use crate::green::GreenNode;
+use crate::syntax::SyntaxKind;
+use crate::tokens::{Children, Comma};
 use crate::tokens::node::Node;
 use crate::tokens::offset::Offset;
 use crate::tokens::value::Value;
 use crate::width::Width;
 
-#[derive(Debug)]
-pub struct Item {
-    pub item: Offset<Node<Value>>,
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Item<'g> {
+    pub item: Offset<Node<Value<'g>>>,
     pub comma: Offset<Option<Comma>>,
 }
 
-impl TryParse for Item {
-    type Error = ParseError;
-
-    fn try_parse(content: Buffer<'_>) -> Result<Self, Self::Error> {
-        let item = Offset::<Node<Value>>::try_parse(content.offset(0)).unwrap();
-        let comma = Offset::<Option<Comma>>::try_parse(content.offset(item.width())).unwrap();
-        Ok(Item { item, comma })
+impl<'g> Item<'g> {
+    pub fn new(node: &'g GreenNode) -> Self {
+        let mut children = Children::new(node);
+        let item = children.next().map_value(Node::value);
+        let comma = children.punctuation(SyntaxKind::Comma, Comma);
+        Item { item, comma }
     }
 }
 
-impl Width for Item {
+impl Width for Item<'_> {
     fn width(&self) -> u32 {
         self.item.width() + self.comma.width()
     }
 }
-
-impl Linearize for Item {
-    fn linearize<'a>(&'a self, source: &'a str, buf: &mut LinearizeBuffer<'a>) {
-        self.item.linearize(source, buf);
-        let offset = (self.item.width() + self.comma.0) as usize;
-        self.comma.linearize(&source[offset..], buf);
-    }
-}
\ No newline at end of file
@@ -0,0 +1,33 @@
+use crate::green::GreenToken;
+use crate::parse::ErrorCode;
+use crate::syntax::SyntaxKind;
+use crate::tokens;
+use crate::tokens::node::Node;
+use crate::width::Width;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Key {
+    String(tokens::String),
+    Identifier(tokens::Identifier),
+    Invalid(ErrorCode),
+}
+
+impl Node<Key> {
+    pub fn key(token: &GreenToken) -> Self {
+        let key = match (token.kind(), token.code()) {
+            (SyntaxKind::String, code) => Key::String(tokens::String { malformed: code.is_some() }),
+            (SyntaxKind::Identifier, _) => Key::Identifier(tokens::Identifier),
+            (_, code) => Key::Invalid(code.expect("an error token has a code")),
+        };
+        Node(token.width(), key)
+    }
+}
+
+/// ECMAScript identifiers, without `\u` escapes.
+pub(crate) fn is_identifier_start(c: char) -> bool {
+    c.is_alphabetic() || c == '$' || c == '_'
+}
+
+pub(crate) fn is_identifier_part(c: char) -> bool {
+    c.is_alphanumeric() || c == '$' || c == '_'
+}
@@ -1,70 +1,107 @@
-use crate::Linearize;
+//! Typed views over the green tree, in the shape the parser reads the input: every token or member
+//! is an [`Offset`] of the trivia in front of it, and every variable-length one is a [`Node`] with
+//! its width. A view decodes one node's children at a time, so walking a tree with them never
+//! builds more than one level.
+use std::iter::Peekable;
+use std::slice;
+use crate::green::{GreenElement, GreenNode};
+use crate::syntax::SyntaxKind;
+use crate::width::Width;
 
 mod pair;
+mod key;
 mod object;
 mod item;
 mod array;
 mod value;
 mod offset;
 mod node;
+mod root;
 
 pub use pair::*;
+pub use key::*;
 pub use object::*;
 pub use item::*;
 pub use array::*;
 pub use offset::*;
 pub use value::*;
 pub use node::*;
+pub use root::*;
 
 macro_rules! define_token {
-    ($name:ident, $char:expr) => {
-        #[derive(Debug)]
+    ($name:ident) => {
+        #[derive(Debug, Clone, Copy, PartialEq)]
         pub struct $name;
 
-        impl $crate::TryParse for $name {
-            type Error = $crate::parse::ParseError;
-            fn try_parse(mut content: $crate::Buffer<'_>) -> Result<Self, $crate::parse::ParseError> {
-                if !content.starts_with($char) {
-                    return Err($crate::parse::ParseError::new(concat!(
-                        "Expected '",
-                        stringify!($char),
-                        "'"
-                    )));
-                }
-                // content.slice_start(1);
-                Ok(Self)
-            }
-        }
-
         impl $crate::Width for $name {
             fn width(&self) -> u32 {
                 1
             }
         }
-
-        impl $crate::Linearize for $name {
-            fn linearize<'a>(&self, source: &'a str, buf: &mut $crate::LinearizeBuffer<'a>) {
-                buf.push((&$name as &dyn std::fmt::Debug, source));
-            }
-        }
     };
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Boolean;
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Null;
 
-define_token!(Colon, ':');
-define_token!(LeftBrace, '{');
-define_token!(RightBrace, '}');
-define_token!(LeftBracket, '[');
-define_token!(RightBracket, ']');
-define_token!(Comma, ',');
+/// An unquoted JSON5 object key.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Identifier;
 
-#[derive(Debug)]
-pub struct Trivia;
-#[derive(Debug)]
-pub struct String;
-#[derive(Debug)]
-pub struct Invalid(crate::parse::ParseError);
-#[derive(Debug)]
+define_token!(Colon);
+define_token!(LeftBrace);
+define_token!(RightBrace);
+define_token!(LeftBracket);
+define_token!(RightBracket);
+define_token!(Comma);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Trivia {
+    Whitespace,
+    /// A `//` comment, including the newline that ends it.
+    LineComment,
+    BlockComment,
+}
+/// `malformed` is set if the string contains invalid escapes or raw control characters.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct String {
+    pub malformed: bool,
+}
+/// Content after the top-level value.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Invalid(pub crate::parse::ErrorCode);
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Number;
+
+/// Reads the children of a node in order.
+struct Children<'g>(Peekable<slice::Iter<'g, GreenElement>>);
+
+impl<'g> Children<'g> {
+    fn new(node: &'g GreenNode) -> Self {
+        Children(node.children().iter().peekable())
+    }
+
+    /// The width of the trivia up to the next token or node.
+    fn trivia(&mut self) -> u32 {
+        let mut width = 0;
+        while let Some(trivia) = self.0.next_if(|c| c.kind().is_trivia()) {
+            width += trivia.width();
+        }
+        width
+    }
+
+    /// The trivia in front of the next element, and the element.
+    fn next(&mut self) -> Offset<&'g GreenElement> {
+        let trivia = self.trivia();
+        Offset(trivia, self.0.next().expect("the node has the element"))
+    }
+
+    /// The trivia in front of `kind`, and whether it is there. Without it, the trivia is all there
+    /// is left before the next element.
+    fn punctuation<T>(&mut self, kind: SyntaxKind, token: T) -> Offset<Option<T>> {
+        let trivia = self.trivia();
+        Offset(trivia, self.0.next_if(|c| c.kind() == kind).map(|_| token))
+    }
+}
@@ -1,51 +1,103 @@
-use crate::{Linearize, LinearizeBuffer};
-use crate::buffer::Buffer;
-use crate::parse::{ParseError, TryParse};
-use crate::tokens::{LeftBrace, Node, RightBrace};
+use crate::green::{GreenElement, GreenNode};
+use crate::syntax::SyntaxKind;
+use crate::tokens::{LeftBrace, RightBrace};
 use crate::tokens::offset::Offset;
 use crate::tokens::pair::Pair;
 use crate::width::Width;
 
-#[derive(Debug)]
-pub struct Object {
-    pub left_brace: Offset<LeftBrace>,
-    pub pairs: Vec<Pair>,
-    pub right_brace: Offset<RightBrace>,
-}
+/// An object in the green tree. An unclosed object has no `}` and ends after its last member, or
+/// the trivia after it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Object<'g>(pub &'g GreenNode);
+
+impl<'g> Object<'g> {
+    pub fn left_brace(&self) -> Offset<LeftBrace> {
+        Offset(0, LeftBrace)
+    }
+
+    /// The members, decoded from the tree each time they are asked for.
+    pub fn pairs(&self) -> Vec<Pair<'g>> {
+        self.0.children().iter().filter_map(GreenElement::as_node).map(Pair::new).collect()
+    }
 
-impl TryParse for Node<Object> {
-    type Error = ParseError;
-
-    fn try_parse(content: Buffer<'_>) -> Result<Self, Self::Error> {
-        let left_brace = Offset::<LeftBrace>::try_parse(content.offset(0))?;
-        let mut pairs = Vec::new();
-        let mut offset = left_brace.width();
-        while let Ok(p) = Pair::try_parse(content.offset(offset)) {
-            offset += p.width();
-            pairs.push(p);
-        }
-        let right_brace = Offset::<RightBrace>::try_parse(content.offset(offset))?;
-        offset += right_brace.width();
-        let object = Object {
-            left_brace,
-            pairs,
-            right_brace,
-        };
-        // content.slice_start(len);
-        Ok(Node(offset, object))
+    /// The `}`, after the trivia that does not belong to the last pair.
+    pub fn right_brace(&self) -> Offset<Option<RightBrace>> {
+        let closed = self.0.children().last().map(GreenElement::kind) == Some(SyntaxKind::RightBrace);
+        Offset(closing_trivia(self.0), closed.then_some(RightBrace))
+    }
+
+    pub fn has_error(&self) -> bool {
+        self.0.has_error()
     }
 }
 
-impl Linearize for Object {
-    fn linearize<'a>(&'a self, source: &'a str, buf: &mut LinearizeBuffer<'a>) {
-        let mut offset = 0;
-        self.left_brace.linearize(&source[offset..offset + 1], buf);
-        offset += self.left_brace.width() as usize;
-        for p in &self.pairs {
-            p.linearize(&source[offset..offset + p.width() as usize], buf);
-            offset += p.width() as usize;
-        }
-        // dbg!(source, offset);
-        self.right_brace.linearize(&source[offset..], buf);
-    }
-}
\ No newline at end of file
+/// The trivia in front of the closing bracket of a container, or at its end if it is unclosed.
+pub(crate) fn closing_trivia(node: &GreenNode) -> u32 {
+    let children = node.children();
+    let closed = matches!(children.last().map(GreenElement::kind), Some(SyntaxKind::RightBrace | SyntaxKind::RightBracket));
+    children[..children.len() - closed as usize].iter().rev().take_while(|c| c.kind().is_trivia()).map(Width::width).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tokens::{self, Key, Value};
+    use crate::width::Width;
+
+    fn object(text: &str) -> crate::Source {
+        crate::parse(text.to_string())
+    }
+
+    #[test]
+    fn test_unclosed() {
+        let s = object("{\"a\": 1, \"b\": {\"c\": 2}");
+        let Value::Object(o) = s.tree().value.1 .1 else { panic!() };
+        assert!(o.right_brace().1.is_none());
+        let pairs = o.pairs();
+        assert_eq!(pairs.len(), 2);
+        let Value::Object(inner) = pairs[1].value.1 .1 else { panic!() };
+        assert!(inner.right_brace().1.is_some());
+        let d: Vec<_> = s.diagnostics().iter().map(|d| (d.code, d.range.start, d.range.end)).collect();
+        assert_eq!(d, vec![(crate::ErrorCode::UnclosedObject, 0, 1)]);
+    }
+
+    #[test]
+    fn test_invalid_value_keeps_following_pairs() {
+        let s = object("{\"a\": tru, \"b\": 2}");
+        let Value::Object(o) = s.tree().value.1 .1 else { panic!() };
+        let pairs = o.pairs();
+        assert_eq!(pairs.len(), 2);
+        assert_eq!(pairs[0].value.1 .0, 3);
+        assert!(matches!(pairs[0].value.1 .1, Value::Invalid(_)));
+        assert!(matches!(pairs[1].value.1 .1, Value::Number(_)));
+    }
+
+    #[test]
+    fn test_invalid_key() {
+        let s = object("{ x: 1, ]: 2, \"c\": 3 }");
+        let Value::Object(o) = s.tree().value.1 .1 else { panic!() };
+        let pairs = o.pairs();
+        assert_eq!(pairs.len(), 3);
+        assert!(matches!(pairs[0].key.1 .1, Key::Invalid(_)));
+        assert!(pairs[0].colon.1.is_some());
+        assert!(matches!(pairs[0].value.1 .1, Value::Number(_)));
+        assert!(matches!(pairs[1].key.1 .1, Key::Invalid(_)));
+        assert!(matches!(pairs[2].key.1 .1, Key::String(tokens::String { malformed: false })));
+        assert!(pairs[2].colon.1.is_some());
+        assert!(matches!(pairs[2].value.1 .1, Value::Number(_)));
+    }
+
+    #[test]
+    fn test_missing_colon_and_value() {
+        let s = object("{\"a\" 1, \"b\":, \"c\"}");
+        let Value::Object(o) = s.tree().value.1 .1 else { panic!() };
+        let pairs = o.pairs();
+        assert_eq!(pairs.len(), 3);
+        assert!(pairs[0].colon.1.is_none());
+        assert!(!pairs[0].value.1 .1.has_error());
+        assert_eq!(pairs[1].value.1 .0, 0);
+        assert!(pairs[1].value.1 .1.has_error());
+        assert!(pairs[2].colon.1.is_none());
+        assert!(pairs[2].value.1 .1.has_error());
+        assert_eq!(o.0.width(), 18);
+    }
+}
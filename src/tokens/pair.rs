@@ -1,51 +1,35 @@
-use crate::{tokens, Linearize, LinearizeBuffer};
-use crate::buffer::Buffer;
-use crate::parse::{ParseError, TryParse};
-use crate::tokens::{Colon, Comma};
+use crate::green::GreenNode;
+use crate::syntax::SyntaxKind;
+use crate::tokens::{Children, Colon, Comma, Key};
 use crate::tokens::node::Node;
 use crate::tokens::offset::Offset;
 use crate::tokens::value::Value;
 use crate::width::Width;
 
-#[derive(Debug)]
-pub struct Pair {
-    pub key: Offset<Node<tokens::String>>,
-    pub colon: Offset<Colon>,
-    pub value: Offset<Node<Value>>,
+/// A member of an object. A pair is there whenever there is content left in the object: a
+/// malformed key is `Key::Invalid`, a missing colon is `None` and a malformed value is
+/// `Value::Invalid`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Pair<'g> {
+    pub key: Offset<Node<Key>>,
+    pub colon: Offset<Option<Colon>>,
+    pub value: Offset<Node<Value<'g>>>,
     pub comma: Offset<Option<Comma>>,
 }
 
-impl TryParse for Pair {
-    type Error = ParseError;
-
-    fn try_parse(content: Buffer<'_>) -> Result<Self, Self::Error> {
-        let key = Offset::<Node<tokens::String>>::try_parse(content.offset(0))?;
-        let mut offset = key.width();
-        let colon = Offset::<Colon>::try_parse(content.offset(offset))?;
-        offset += colon.width();
-        let value = Offset::<Node<Value>>::try_parse(content.offset(offset)).unwrap();
-        offset += value.width();
-        let comma = Offset::<Option<Comma>>::try_parse(content.offset(offset)).unwrap();
-        Ok(Pair {
-            key,
-            colon,
-            value,
-            comma,
-        })
+impl<'g> Pair<'g> {
+    pub fn new(node: &'g GreenNode) -> Self {
+        let mut children = Children::new(node);
+        let key = children.next().map_value(|key| Node::key(key.as_token().expect("a key is a token")));
+        let colon = children.punctuation(SyntaxKind::Colon, Colon);
+        let value = children.next().map_value(Node::value);
+        let comma = children.punctuation(SyntaxKind::Comma, Comma);
+        Pair { key, colon, value, comma }
     }
 }
 
-impl Linearize for Pair {
-    fn linearize<'a>(&'a self, source: &'a str, buf: &mut LinearizeBuffer<'a>) {
-        self.key.linearize(source, buf);
-        self.colon.linearize(source, buf);
-        self.value.linearize(source, buf);
-        self.comma.linearize(source, buf);
-    }
-}
-
-impl Width for Pair {
+impl Width for Pair<'_> {
     fn width(&self) -> u32 {
         self.key.width() + self.colon.width() + self.value.width() + self.comma.width()
     }
-}
\ No newline at end of file
+}
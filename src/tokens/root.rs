@@ -0,0 +1,35 @@
+use crate::green::GreenNode;
+use crate::tokens;
+use crate::tokens::Children;
+use crate::tokens::node::Node;
+use crate::tokens::offset::Offset;
+use crate::tokens::value::Value;
+use crate::width::Width;
+
+/// The whole document. Whatever follows the top-level value belongs to the root as well, so that
+/// the tree always covers the entire input.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Root<'g> {
+    pub value: Offset<Node<Value<'g>>>,
+    /// The trivia after the value, and anything else up to the end of the input.
+    pub trailing: Offset<Option<Node<tokens::Invalid>>>,
+}
+
+impl<'g> Root<'g> {
+    pub fn new(node: &'g GreenNode) -> Self {
+        let mut children = Children::new(node);
+        let value = children.next().map_value(Node::value);
+        let trivia = children.trivia();
+        let trailing = children.0.next().map(|t| {
+            let code = t.as_token().and_then(|t| t.code()).expect("trailing content is an error token");
+            Node(t.width(), tokens::Invalid(code))
+        });
+        Root { value, trailing: Offset(trivia, trailing) }
+    }
+}
+
+impl Width for Root<'_> {
+    fn width(&self) -> u32 {
+        self.value.width() + self.trailing.width()
+    }
+}
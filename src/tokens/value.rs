@@ -1,38 +1,52 @@
+use crate::green::GreenElement;
+use crate::parse::ErrorCode;
+use crate::syntax::SyntaxKind;
 use crate::tokens;
-use crate::parse::ParseError;
-use crate::tokens::{Array, Boolean, Number, Object};
+use crate::tokens::{Array, Boolean, Node, Null, Number, Object};
+use crate::width::Width;
 
-impl Value {
+impl Value<'_> {
+    /// Whether this value or anything nested in it failed to parse, including members that are
+    /// missing the comma separating them from the next one.
     pub fn has_error(&self) -> bool {
         match &self {
             Value::Invalid(_) => true,
-            Value::Object(o) => {
-                for pair in &o.pairs {
-                    if pair.value.1 .1.has_error() {
-                        return true;
-                    }
-                }
-                false
-            }
-            Value::Array(a) => {
-                for value in &a.values {
-                    if value.item.1 .1.has_error() {
-                        return true;
-                    }
-                }
-                false
-            }
+            Value::String(s) => s.malformed,
+            Value::Object(o) => o.has_error(),
+            Value::Array(a) => a.has_error(),
             _ => false,
         }
     }
 }
 
-#[derive(Debug)]
-pub enum Value {
+impl<'g> Node<Value<'g>> {
+    /// The view of a value in the green tree.
+    pub fn value(element: &'g GreenElement) -> Self {
+        let value = match element {
+            GreenElement::Node(node) if node.kind() == SyntaxKind::Object => Value::Object(Object(node)),
+            GreenElement::Node(node) => Value::Array(Array(node)),
+            GreenElement::Token(token) => match token.kind() {
+                SyntaxKind::Boolean => Value::Boolean(Boolean),
+                SyntaxKind::Null => Value::Null(Null),
+                SyntaxKind::Number => Value::Number(Number),
+                SyntaxKind::String => Value::String(tokens::String { malformed: token.code().is_some() }),
+                _ => Value::Invalid(token.code().expect("an error token has a code")),
+            },
+        };
+        Node(element.width(), value)
+    }
+}
+
+/// A value, as a view into the green tree. Objects and arrays are handles whose members are read
+/// when asked for.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Value<'g> {
     Boolean(Boolean),
+    Null(Null),
     String(tokens::String),
     Number(Number),
-    Object(Object),
-    Array(Array),
-    Invalid(ParseError),
-}
\ No newline at end of file
+    Object(Object<'g>),
+    Array(Array<'g>),
+    /// A malformed value. The message is recovered with [`crate::parse::error`].
+    Invalid(ErrorCode),
+}
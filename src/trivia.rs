@@ -0,0 +1,128 @@
+use crate::escape;
+use crate::tokens::Trivia;
+
+/// The trivia token at the start of `content` and its length, if there is one. An unterminated
+/// block comment runs to the end of the input. Only JSON5 allows whitespace other than space, tab,
+/// line feed and carriage return.
+pub(crate) fn next(content: &str, json5: bool) -> Option<(Trivia, u32)> {
+    let bytes = content.as_bytes();
+    let len = match bytes {
+        [b'/', b'/', ..] => {
+            let len = content.find('\n').map(|i| i + 1).unwrap_or(content.len());
+            return Some((Trivia::LineComment, len as u32));
+        }
+        [b'/', b'*', ..] => {
+            let len = content[2..].find("*/").map(|i| i + 4).unwrap_or(content.len());
+            return Some((Trivia::BlockComment, len as u32));
+        }
+        _ if !json5 => bytes.iter().take_while(|b| matches!(b, b' ' | b'\t' | b'\n' | b'\r')).count(),
+        _ => {
+            // ASCII whitespace is by far the most common, so only fall back to decoding characters
+            // when there is more after it.
+            let ascii = bytes.iter().take_while(|&&b| b.is_ascii_whitespace() || b == 0x0B).count();
+            match bytes.get(ascii) {
+                Some(b) if !b.is_ascii() => content.len() - content.trim_start_matches(char::is_whitespace).len(),
+                _ => ascii,
+            }
+        }
+    };
+    (len > 0).then_some((Trivia::Whitespace, len as u32))
+}
+
+/// Splits a run of trivia into its tokens. The run was parsed as trivia already, so any whitespace
+/// in it is allowed.
+pub(crate) fn split(mut trivia: &str) -> impl Iterator<Item = (Trivia, &str)> {
+    std::iter::from_fn(move || {
+        let (kind, len) = next(trivia, true)?;
+        let (token, rest) = trivia.split_at(len as usize);
+        trivia = rest;
+        Some((kind, token))
+    })
+}
+
+pub(crate) fn is_unterminated(kind: Trivia, token: &str) -> bool {
+    kind == Trivia::BlockComment && (token.len() < 4 || !token.ends_with("*/"))
+}
+
+/// The start of an unterminated block comment in `content`, skipping over string literals. Such a
+/// comment runs to the end of the input, so it can only end up in leading trivia or in the span of
+/// an unclosed container.
+pub(crate) fn unterminated_comment(content: &str, json5: bool) -> Option<u32> {
+    let mut offset = 0;
+    while offset < content.len() {
+        let rest = &content[offset..];
+        let step = match rest.as_bytes()[0] {
+            c if c == b'"' || json5 && c == b'\'' => escape::scan(rest, json5, |_, _, _| {})
+                .map(|len| len as usize)
+                .unwrap_or_else(|| rest.find('\n').unwrap_or(rest.len())),
+            b'/' => match next(rest, json5) {
+                Some((kind, len)) if is_unterminated(kind, &rest[..len as usize]) => return Some(offset as u32),
+                Some((_, len)) => len as usize,
+                None => 1,
+            },
+            _ => rest.chars().next().map_or(1, char::len_utf8),
+        };
+        offset += step.max(1);
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Dialect;
+
+    /// The length of the run of trivia at the start of `content`.
+    fn len(content: &str) -> u32 {
+        let mut offset = 0;
+        while let Some((_, len)) = next(&content[offset as usize..], true) {
+            offset += len;
+        }
+        offset
+    }
+
+    #[test]
+    fn test_split() {
+        let s = " \n// line\n/* block\n */\t/**/x";
+        let len = len(s);
+        assert_eq!(&s[len as usize..], "x");
+        let tokens: Vec<_> = split(&s[..len as usize]).collect();
+        assert_eq!(tokens, vec![
+            (Trivia::Whitespace, " \n"),
+            (Trivia::LineComment, "// line\n"),
+            (Trivia::BlockComment, "/* block\n */"),
+            (Trivia::Whitespace, "\t"),
+            (Trivia::BlockComment, "/**/"),
+        ]);
+    }
+
+    #[test]
+    fn test_not_a_comment() {
+        assert_eq!(len("/x"), 0);
+        assert_eq!(len(" /"), 1);
+        assert_eq!(len("/*/"), 3);
+        assert!(is_unterminated(Trivia::BlockComment, "/*/"));
+        assert!(!is_unterminated(Trivia::BlockComment, "/**/"));
+    }
+
+    #[test]
+    fn test_whitespace_per_dialect() {
+        assert_eq!(next(" \t\r\n1", false), Some((Trivia::Whitespace, 4)));
+        for c in ['\u{a0}', '\u{c}', '\u{b}'] {
+            assert_eq!(next(&format!("{}1", c), false), None);
+            assert_eq!(next(&format!("{}1", c), true), Some((Trivia::Whitespace, c.len_utf8() as u32)));
+            let text = format!("[1,{}2]", c);
+            for dialect in [Dialect::Json, Dialect::Jsonc, Dialect::Json5] {
+                let s = crate::parse_with(text.clone(), dialect.into());
+                assert_eq!(s.has_error(), dialect != Dialect::Json5, "{:?} in {:?}", c, dialect);
+            }
+        }
+    }
+
+    #[test]
+    fn test_unterminated_comment() {
+        assert_eq!(unterminated_comment("[1, /* a */ 2, \"/*\" /* b", false), Some(20));
+        assert_eq!(unterminated_comment("[\"é\", // /*\n", false), None);
+        assert_eq!(unterminated_comment("['/*'", true), None);
+    }
+}
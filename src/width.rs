@@ -1,5 +1,3 @@
-use crate::tokens::Node;
-use crate::tokens::Offset;
 
 
 pub trait Width {